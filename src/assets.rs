@@ -0,0 +1,283 @@
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use lol_html::html_content::Element;
+use lol_html::{RewriteStrSettings, element, rewrite_str};
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use crate::config::{Options, OutputFormat};
+use crate::warnings::Warnings;
+
+/// How references to local files (images, fonts) are written in the generated HTML.
+#[derive(Debug, Clone, Copy)]
+pub enum AssetMode<'a> {
+    /// Absolute `file://` URLs, so they don't depend on the directory the worksheet is built
+    /// from. Remote files are downloaded into `cache_dir` so later builds work offline.
+    Resolve { cache_dir: &'a Path },
+    /// Data URIs, so the HTML works as a single file
+    Embed,
+    /// Copied into `assets_dir` next to the HTML file, and linked relatively. Copies are named
+    /// with a hash of their contents, so files with the same name from different directories
+    /// don't overwrite each other.
+    Copy {
+        html_dir: &'a Path,
+        assets_dir: &'a Path,
+    },
+}
+
+impl<'a> AssetMode<'a> {
+    pub fn for_options(options: &'a Options) -> Self {
+        if options.self_contained {
+            return AssetMode::Embed;
+        }
+        match (&options.output_format, &options.assets_dir) {
+            (OutputFormat::Html, Some(assets_dir)) => AssetMode::Copy {
+                html_dir: options
+                    .output_file
+                    .parent()
+                    .unwrap_or_else(|| Path::new(".")),
+                assets_dir,
+            },
+            _ => AssetMode::Resolve {
+                cache_dir: &options.cache_dir,
+            },
+        }
+    }
+}
+
+/// Rewrite local `<img>` sources, resolving relative paths against `base_dir`.
+pub fn rewrite_images(
+    html: &str,
+    base_dir: &Path,
+    mode: AssetMode,
+    warnings: &Warnings,
+) -> Result<String> {
+    let html = rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![element!("img[src]", |el: &mut Element| {
+                let src = el.get_attribute("src").unwrap_or_default();
+                if let Some(url) = rewrite_url(&src, base_dir, mode, warnings)? {
+                    el.set_attribute("src", &url)?;
+                }
+                Ok(())
+            })],
+            ..RewriteStrSettings::new()
+        },
+    )?;
+    Ok(html)
+}
+
+/// Rewrite local `url(...)` references in CSS (fonts, background images).
+pub fn rewrite_css_urls(
+    css: &str,
+    base_dir: &Path,
+    mode: AssetMode,
+    warnings: &Warnings,
+) -> Result<String> {
+    let mut result = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("url(") {
+        let after_open = &rest[start + 4..];
+        let Some(end) = after_open.find(')') else {
+            break;
+        };
+        let url = after_open[..end].trim().trim_matches(['"', '\'']);
+        result.push_str(&rest[..start]);
+        match rewrite_url(url, base_dir, mode, warnings)? {
+            Some(url) => result.push_str(&format!("url(\"{url}\")")),
+            None => result.push_str(&rest[start..start + end + 5]),
+        }
+        rest = &after_open[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+// The new URL for a local file, or `None` to leave the URL alone
+fn rewrite_url(
+    url: &str,
+    base_dir: &Path,
+    mode: AssetMode,
+    warnings: &Warnings,
+) -> Result<Option<String>> {
+    match mode {
+        AssetMode::Resolve { cache_dir } => {
+            let path = if url.starts_with("http://") || url.starts_with("https://") {
+                match fetch_cached(url, cache_dir) {
+                    Ok(path) => path,
+                    Err(e) => {
+                        warnings.warn(format!("{e:#}"), "leaving it as a link")?;
+                        return Ok(None);
+                    }
+                }
+            } else {
+                let Some(path) = local_file(url, base_dir, warnings)? else {
+                    return Ok(None);
+                };
+                path
+            };
+            let path = std::path::absolute(path)?;
+            Ok(Url::from_file_path(&path).ok().map(String::from))
+        }
+        AssetMode::Embed => {
+            let Some(path) = local_file(url, base_dir, warnings)? else {
+                return Ok(None);
+            };
+            let data = fs::read(&path).context(format!("Failed to read {}", path.display()))?;
+            Ok(Some(format!(
+                "data:{};base64,{}",
+                mime_type(&path),
+                STANDARD.encode(data)
+            )))
+        }
+        AssetMode::Copy {
+            html_dir,
+            assets_dir,
+        } => {
+            let Some(path) = local_file(url, base_dir, warnings)? else {
+                return Ok(None);
+            };
+            let data = fs::read(&path).context(format!("Failed to read {}", path.display()))?;
+            let hash = format!("{:x}", Sha256::digest(&data));
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let file_name = match path.extension() {
+                Some(ext) => format!("{stem}-{}.{}", &hash[..12], ext.to_string_lossy()),
+                None => format!("{stem}-{}", &hash[..12]),
+            };
+            let target_dir = html_dir.join(assets_dir);
+            fs::create_dir_all(&target_dir).context(format!(
+                "Failed to create assets directory: {}",
+                target_dir.display()
+            ))?;
+            // A file with the same name already has the same contents
+            let target = target_dir.join(&file_name);
+            if !target.is_file() {
+                fs::write(&target, data).context(format!("Failed to copy {}", path.display()))?;
+            }
+            let url = assets_dir
+                .join(file_name)
+                .to_string_lossy()
+                .replace('\\', "/");
+            Ok(Some(url))
+        }
+    }
+}
+
+// The existing file a local URL refers to, warning about one that's missing
+fn local_file(url: &str, base_dir: &Path, warnings: &Warnings) -> Result<Option<PathBuf>> {
+    let Some(path) = local_path(url, base_dir) else {
+        return Ok(None);
+    };
+    if !path.is_file() {
+        warnings.warn(
+            format!("{} not found", path.display()),
+            "leaving it as a link",
+        )?;
+        return Ok(None);
+    }
+    Ok(Some(path))
+}
+
+/// Download a remote file into the cache, unless it's already there.
+pub fn fetch_cached(url: &str, cache_dir: &Path) -> Result<PathBuf> {
+    // Name cached files by a hash of their URL, keeping the extension so the type is clear.
+    // SHA-256 rather than the standard library's hasher, whose output can change between Rust
+    // releases.
+    let hash = format!("{:x}", Sha256::digest(url));
+    let extension = Path::new(url.split(['#', '?']).next().unwrap_or(url))
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let path = cache_dir.join(format!("{}{extension}", &hash[..16]));
+    if path.is_file() {
+        return Ok(path);
+    }
+
+    let data = ureq::get(url)
+        .call()
+        .and_then(|mut response| response.body_mut().read_to_vec())
+        .context(format!("Failed to download {url}"))?;
+    fs::create_dir_all(cache_dir).context(format!(
+        "Failed to create cache directory: {}",
+        cache_dir.display()
+    ))?;
+    // Files are built in parallel, so another build could be reading or downloading the same
+    // file. Renaming a finished download into place means nobody sees a partial one.
+    let temp_path = cache_dir.join(format!(
+        ".{}-{}-{:?}.tmp",
+        &hash[..16],
+        std::process::id(),
+        std::thread::current().id()
+    ));
+    fs::write(&temp_path, data).context(format!("Failed to write {}", temp_path.display()))?;
+    fs::rename(&temp_path, &path).context(format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Local image files that HTML links to with `file:` URLs, as written by
+/// [`AssetMode::Resolve`]. Downloaded images in the cache are left out, and paths are made
+/// relative to the current directory where they can be.
+pub fn local_images(html: &str, cache_dir: &Path) -> Result<Vec<PathBuf>> {
+    let current_dir = std::env::current_dir()?;
+    let cache_dir = std::path::absolute(cache_dir)?;
+    let images = RefCell::new(vec![]);
+    rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![element!("img[src]", |el: &mut Element| {
+                let src = el.get_attribute("src").unwrap_or_default();
+                let path = Url::parse(&src)
+                    .ok()
+                    .filter(|url| url.scheme() == "file")
+                    .and_then(|url| url.to_file_path().ok());
+                if let Some(path) = path.filter(|path| !path.starts_with(&cache_dir)) {
+                    let path = path.strip_prefix(&current_dir).unwrap_or(&path);
+                    images.borrow_mut().push(path.to_path_buf());
+                }
+                Ok(())
+            })],
+            ..RewriteStrSettings::new()
+        },
+    )?;
+
+    Ok(images.into_inner())
+}
+
+/// The file a URL refers to, if it's a relative path or a `file://` URL.
+pub fn local_path(url: &str, base_dir: &Path) -> Option<PathBuf> {
+    if url.starts_with("file:") {
+        return Url::parse(url).ok()?.to_file_path().ok();
+    }
+    // Drop any fragment or query, as in `font.svg#icons`
+    is_local(url).then(|| base_dir.join(url.split(['#', '?']).next().unwrap_or(url)))
+}
+
+pub fn is_local(url: &str) -> bool {
+    !(url.is_empty() || url.contains("://") || url.starts_with("data:") || url.starts_with('#'))
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "css" => "text/css",
+        _ => "application/octet-stream",
+    }
+}
@@ -0,0 +1,232 @@
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+
+use crate::config::Options;
+use crate::context::{ChoiceAnswer, RenderContext};
+use crate::html_gen;
+
+// Layout in millimetres. The printable area fits both Letter and A4 with 15mm margins.
+const PAGE_WIDTH: f64 = 180.0;
+const PAGE_HEIGHT: f64 = 245.0;
+const MARKER_SIZE: f64 = 6.0;
+const ROW_SPACING: f64 = 7.0;
+const BUBBLE_SPACING: f64 = 7.0;
+const BUBBLE_RADIUS: f64 = 2.4;
+// Questions are numbered on the left of each column, with bubbles after the number
+const COLUMN_LEFT: f64 = 12.0;
+const BUBBLES_LEFT: f64 = 11.0;
+// The narrowest column, and the space between the last bubble and the next column
+const COLUMN_WIDTH: f64 = 58.0;
+const COLUMN_GAP: f64 = 5.0;
+const MIN_CHOICES: usize = 5;
+const ID_DIGITS: usize = 6;
+// Where questions start on the first page (below the header and ID grid), and later pages
+const FIRST_PAGE_TOP: f64 = 100.0;
+const PAGE_TOP: f64 = 16.0;
+const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Render a bubble answer sheet for the worksheet's multiple-choice questions, with the correct
+/// bubbles filled in if `show_answers` is set.
+///
+/// Every question gets at least A–E bubbles, and columns are sized to fit the question with
+/// the most choices. Filled corner markers let scanning software align the page, and the layout
+/// ignores the user stylesheet so positions are exact.
+pub fn generate_bubble_sheet(options: &Options, show_answers: bool) -> Result<String> {
+    let mut ctx = RenderContext::new(options, false);
+    html_gen::generate_html_body(options, &mut ctx)?;
+    if ctx.choice_answers.is_empty() {
+        return Err(anyhow!(
+            "Can't make a bubble sheet: the worksheet has no multiple-choice questions"
+        ));
+    }
+    // Bubbles for the correct letters are filled in, so the student copy has none
+    if !show_answers {
+        for answer in &mut ctx.choice_answers {
+            answer.correct.clear();
+        }
+    }
+
+    let most_choices = ctx
+        .choice_answers
+        .iter()
+        .map(|answer| answer.choices)
+        .max()
+        .unwrap_or(0)
+        .max(MIN_CHOICES);
+    let column_width =
+        (BUBBLES_LEFT + most_choices as f64 * BUBBLE_SPACING + COLUMN_GAP).max(COLUMN_WIDTH);
+    // The last column's bubbles have to stay clear of the alignment markers
+    let right = PAGE_WIDTH - MARKER_SIZE - 2.0;
+    let max_choices =
+        ((right - COLUMN_LEFT - BUBBLES_LEFT - BUBBLE_RADIUS) / BUBBLE_SPACING) as usize + 1;
+    if most_choices > max_choices {
+        return Err(anyhow!(
+            "Can't make a bubble sheet: a question has {most_choices} choices, \
+             but at most {max_choices} fit across the page"
+        ));
+    }
+    let bubbles_width = BUBBLES_LEFT + (most_choices - 1) as f64 * BUBBLE_SPACING + BUBBLE_RADIUS;
+    let columns = ((right - COLUMN_LEFT - bubbles_width) / column_width) as usize + 1;
+
+    let mut pages = vec![];
+    let mut remaining: &[ChoiceAnswer] = &ctx.choice_answers;
+    let mut first_number = 1;
+    while pages.is_empty() || !remaining.is_empty() {
+        let top = if pages.is_empty() {
+            FIRST_PAGE_TOP
+        } else {
+            PAGE_TOP
+        };
+        let rows = ((PAGE_HEIGHT - MARKER_SIZE - top) / ROW_SPACING).floor() as usize;
+        let (questions, rest) = remaining.split_at(remaining.len().min(rows * columns));
+        pages.push(page_svg(
+            pages.is_empty(),
+            options.variant.as_deref(),
+            top,
+            rows,
+            column_width,
+            first_number,
+            questions,
+        )?);
+        first_number += questions.len();
+        remaining = rest;
+    }
+
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <style>
+        @page {{ margin: 15mm; }}
+        body {{ margin: 0; }}
+        svg.bubble-sheet {{ display: block; width: {PAGE_WIDTH}mm; height: {PAGE_HEIGHT}mm; page-break-after: always; }}
+        svg.bubble-sheet:last-child {{ page-break-after: auto; }}
+    </style>
+</head>
+<body>
+{}
+</body>
+</html>"#,
+        pages.join("\n")
+    ))
+}
+
+fn page_svg(
+    is_first: bool,
+    variant: Option<&str>,
+    top: f64,
+    rows: usize,
+    column_width: f64,
+    first_number: usize,
+    questions: &[ChoiceAnswer],
+) -> Result<String> {
+    let mut svg = format!(
+        "<svg class=\"bubble-sheet\" xmlns=\"http://www.w3.org/2000/svg\" \
+         viewBox=\"0 0 {PAGE_WIDTH} {PAGE_HEIGHT}\" font-family=\"sans-serif\" font-size=\"3.2\">\n"
+    );
+
+    // Alignment markers in each corner
+    for (x, y) in [
+        (0.0, 0.0),
+        (PAGE_WIDTH - MARKER_SIZE, 0.0),
+        (0.0, PAGE_HEIGHT - MARKER_SIZE),
+        (PAGE_WIDTH - MARKER_SIZE, PAGE_HEIGHT - MARKER_SIZE),
+    ] {
+        writeln!(
+            svg,
+            "<rect x=\"{x}\" y=\"{y}\" width=\"{MARKER_SIZE}\" height=\"{MARKER_SIZE}\"/>"
+        )?;
+    }
+
+    if is_first {
+        writeln!(
+            svg,
+            "<text x=\"12\" y=\"16\" font-size=\"4.5\">Name</text>\n\
+             <line x1=\"26\" y1=\"16.5\" x2=\"110\" y2=\"16.5\" stroke=\"#000\" stroke-width=\"0.3\"/>"
+        )?;
+        if let Some(variant) = variant {
+            writeln!(
+                svg,
+                "<text x=\"{}\" y=\"16\" font-size=\"4.5\" font-weight=\"bold\" \
+                 text-anchor=\"end\">Form {variant}</text>",
+                PAGE_WIDTH - 12.0
+            )?;
+        }
+        student_id_grid(&mut svg)?;
+    }
+
+    for (index, question) in questions.iter().enumerate() {
+        let x = COLUMN_LEFT + (index / rows) as f64 * column_width;
+        let y = top + (index % rows) as f64 * ROW_SPACING;
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\" font-weight=\"bold\">{}</text>",
+            x + 6.0,
+            y + 1.1,
+            first_number + index
+        )?;
+        for (i, letter) in LETTERS
+            .chars()
+            .take(question.choices.max(MIN_CHOICES))
+            .enumerate()
+        {
+            let x = x + BUBBLES_LEFT + i as f64 * BUBBLE_SPACING;
+            if question.correct.contains(letter) {
+                filled_bubble(&mut svg, x, y)?;
+            } else {
+                bubble(&mut svg, x, y, letter)?;
+            }
+        }
+    }
+
+    svg.push_str("</svg>");
+    Ok(svg)
+}
+
+// A column of 0–9 bubbles for each digit, with a box above to write it in
+fn student_id_grid(svg: &mut String) -> Result<()> {
+    let left = 12.0;
+    let top = 26.0;
+    writeln!(
+        svg,
+        "<text x=\"{left}\" y=\"{top}\" font-weight=\"bold\">Student ID</text>"
+    )?;
+    for digit in 0..ID_DIGITS {
+        let x = left + 3.0 + digit as f64 * BUBBLE_SPACING;
+        writeln!(
+            svg,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"6\" height=\"6\" fill=\"none\" \
+             stroke=\"#000\" stroke-width=\"0.3\"/>",
+            x - 3.0,
+            top + 2.0
+        )?;
+        for value in 0..10 {
+            let y = top + 13.0 + f64::from(value) * 6.0;
+            bubble(svg, x, y, char::from_digit(value, 10).unwrap())?;
+        }
+    }
+    Ok(())
+}
+
+// A bubble marked the way a student would, with its letter covered
+fn filled_bubble(svg: &mut String, x: f64, y: f64) -> Result<()> {
+    writeln!(
+        svg,
+        "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"{BUBBLE_RADIUS}\" stroke=\"#000\" \
+         stroke-width=\"0.3\"/>"
+    )?;
+    Ok(())
+}
+
+fn bubble(svg: &mut String, x: f64, y: f64, label: char) -> Result<()> {
+    writeln!(
+        svg,
+        "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"{BUBBLE_RADIUS}\" fill=\"none\" \
+         stroke=\"#000\" stroke-width=\"0.3\"/>\n\
+         <text x=\"{x:.1}\" y=\"{:.1}\" text-anchor=\"middle\" font-size=\"2.4\">{label}</text>",
+        y + 0.85
+    )?;
+    Ok(())
+}
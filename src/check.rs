@@ -0,0 +1,258 @@
+mod spelling;
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use comrak::nodes::NodeValue;
+use comrak::{Arena, parse_document};
+use lol_html::html_content::Element;
+use lol_html::{RewriteStrSettings, element, rewrite_str};
+use url::Url;
+
+use crate::assets;
+use crate::config::{Args, Options};
+use crate::context::RenderContext;
+use crate::front_matter::read_page;
+use crate::html_gen;
+use crate::include;
+use crate::page::Page;
+
+use spelling::SpellChecker;
+
+// Words the spell checker should accept, one per line, next to the config file
+const ALLOWLIST_FILE_NAME: &str = "allowed-words.txt";
+
+/// What to spell check pages against, for `check --spelling`.
+pub struct SpellingOptions {
+    /// A Hunspell `.dic` file, rather than the installed one for the document's language
+    pub dictionary: Option<PathBuf>,
+    /// Extra words to accept, defaulting to `allowed-words.txt` next to the config
+    pub allowlist: Option<PathBuf>,
+}
+
+/// Something wrong with a page that would spoil the printed worksheet.
+struct Problem {
+    page: PathBuf,
+    message: String,
+}
+
+/// Check every page of every target, printing any problems found.
+///
+/// Fails if there are any, so it can be used in CI.
+pub fn check(args: Args, spelling: Option<SpellingOptions>) -> Result<()> {
+    let allowlist = spelling.as_ref().and_then(|spelling| {
+        spelling.allowlist.clone().or_else(|| {
+            let config_dir = args.config.as_deref().and_then(Path::parent);
+            let path = config_dir
+                .unwrap_or(Path::new("."))
+                .join(ALLOWLIST_FILE_NAME);
+            path.is_file().then_some(path)
+        })
+    });
+    let configs = Options::load_configs(
+        args.config.as_deref(),
+        args.profile.as_deref(),
+        args.target.as_deref(),
+    )?;
+    let mut checked = HashSet::new();
+    let mut problems = vec![];
+    for config in configs {
+        let options = Options::from_args_and_config(args.clone(), config)?;
+        // Targets often share pages, which only need checking once
+        if options.pages.iter().all(|page| checked.contains(page)) {
+            continue;
+        }
+        checked.extend(options.pages.iter().cloned());
+        let spell_checker = match &spelling {
+            Some(spelling) => Some(SpellChecker::load(
+                spelling.dictionary.as_deref(),
+                options.lang.as_deref().unwrap_or("en-US"),
+                allowlist.as_deref(),
+            )?),
+            None => None,
+        };
+        problems.extend(check_document(&options, spell_checker.as_ref()));
+    }
+
+    for problem in &problems {
+        println!("{}: {}", problem.page.display(), problem.message);
+    }
+    if !problems.is_empty() {
+        return Err(anyhow!(
+            "Found {} problem{} in {} page{}",
+            problems.len(),
+            plural(problems.len()),
+            checked.len(),
+            plural(checked.len())
+        ));
+    }
+    println!(
+        "✓ Checked {} page{}, no problems found",
+        checked.len(),
+        plural(checked.len())
+    );
+
+    Ok(())
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 { "" } else { "s" }
+}
+
+// Pages are checked together, since links can point to headings on other pages
+fn check_document(options: &Options, spell_checker: Option<&SpellChecker>) -> Vec<Problem> {
+    let mut ctx = RenderContext::new(options, false);
+    let mut problems = vec![];
+    let mut ids = HashSet::new();
+    let mut fragment_links = vec![];
+    for page_path in &options.pages {
+        let problem = |message: String| Problem {
+            page: page_path.clone(),
+            message,
+        };
+        match check_page(page_path, options, spell_checker, &mut ctx) {
+            Ok(page) => {
+                problems.extend(page.problems.into_iter().map(problem));
+                ids.extend(page.ids);
+                fragment_links.extend(page.fragments.into_iter().map(|f| (page_path, f)));
+            }
+            Err(e) => problems.push(problem(format!("{e:#}"))),
+        }
+    }
+    ids.extend(ctx.heading_ids);
+
+    for (page_path, fragment) in fragment_links {
+        if !ids.contains(&fragment) {
+            problems.push(Problem {
+                page: page_path.clone(),
+                message: format!("Link to #{fragment} doesn't match any heading or id"),
+            });
+        }
+    }
+
+    problems
+}
+
+/// What was found in one page.
+#[derive(Default)]
+struct PageCheck {
+    problems: Vec<String>,
+    /// Element ids, other than the generated heading ids
+    ids: Vec<String>,
+    /// Links within the document, like `#instructions`
+    fragments: Vec<String>,
+}
+
+// Problems that stop a page rendering at all are returned as errors
+fn check_page(
+    page_path: &Path,
+    options: &Options,
+    spell_checker: Option<&SpellChecker>,
+    ctx: &mut RenderContext,
+) -> Result<PageCheck> {
+    let (_, markdown) = read_page(page_path)?;
+    let missing = include::missing_files(&markdown, page_path);
+    if !missing.is_empty() {
+        let problems = missing
+            .iter()
+            .map(|file| format!("Included file {} not found", file.display()))
+            .collect();
+        return Ok(PageCheck {
+            problems,
+            ..PageCheck::default()
+        });
+    }
+
+    let page = Page::load(page_path, options, ctx)?;
+    let problems = check_directives(&page.markdown, ctx);
+    if !problems.is_empty() {
+        return Ok(PageCheck {
+            problems,
+            ..PageCheck::default()
+        });
+    }
+
+    let html = html_gen::render_markdown(&page.markdown, ctx)?;
+    let html = html_gen::add_heading_ids(&html, false, ctx)?;
+    let page_dir = page_path.parent().unwrap_or_else(|| Path::new("."));
+    let mut check = check_html(&html, page_dir)?;
+    for heading in html_gen::find_headings(&html)? {
+        if heading.text.trim().is_empty() {
+            check
+                .problems
+                .push(format!("Empty h{} heading", heading.level));
+        }
+    }
+    if let Some(spell_checker) = spell_checker {
+        for word in spell_checker.misspellings(&html)? {
+            check.problems.push(format!("Possible misspelling: {word}"));
+        }
+    }
+
+    Ok(check)
+}
+
+// Render each directive on its own, so every malformed one is reported
+fn check_directives(markdown: &str, ctx: &mut RenderContext) -> Vec<String> {
+    let arena = Arena::new();
+    let markdown_options = ctx.markdown.clone();
+    let root = parse_document(&arena, markdown, &markdown_options);
+
+    let mut problems = vec![];
+    for node in root.descendants() {
+        if let NodeValue::CodeBlock(block) = &node.data.borrow().value
+            && let Err(e) = html_gen::render_code_block(&block.info, &block.literal, ctx)
+        {
+            problems.push(format!("{e:#}"));
+        }
+    }
+
+    problems
+}
+
+// Missing images and link targets, relative to the page's directory
+fn check_html(html: &str, page_dir: &Path) -> Result<PageCheck> {
+    let check = RefCell::new(PageCheck::default());
+    rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![
+                element!("img[src]", |el: &mut Element| {
+                    let src = el.get_attribute("src").unwrap_or_default();
+                    if let Some(path) = assets::local_path(&src, page_dir)
+                        && !path.is_file()
+                    {
+                        let message = format!("Image {} not found", path.display());
+                        check.borrow_mut().problems.push(message);
+                    }
+                    Ok(())
+                }),
+                element!("a[href]", |el: &mut Element| {
+                    let href = el.get_attribute("href").unwrap_or_default();
+                    if let Some(fragment) = href.strip_prefix('#') {
+                        check.borrow_mut().fragments.push(fragment.to_string());
+                    } else if Url::parse(&href).is_err() {
+                        // Anything with a scheme, like `mailto:`, isn't a local file
+                        if let Some(path) = assets::local_path(&href, page_dir)
+                            && !path.exists()
+                        {
+                            let message = format!("Link to {} is broken", path.display());
+                            check.borrow_mut().problems.push(message);
+                        }
+                    }
+                    Ok(())
+                }),
+                element!("[id]", |el: &mut Element| {
+                    let id = el.get_attribute("id").unwrap_or_default();
+                    check.borrow_mut().ids.push(id);
+                    Ok(())
+                }),
+            ],
+            ..RewriteStrSettings::new()
+        },
+    )?;
+
+    Ok(check.into_inner())
+}
@@ -0,0 +1,153 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use lol_html::html_content::Element;
+use lol_html::{RewriteStrSettings, doc_text, element, rewrite_str};
+use spellbook::Dictionary;
+
+// Where Hunspell dictionaries are usually installed
+const DICTIONARY_DIRS: &[&str] = &[
+    "/usr/share/hunspell",
+    "/usr/share/myspell",
+    "/usr/share/myspell/dicts",
+    "/usr/local/share/hunspell",
+    "/Library/Spelling",
+];
+
+// Markup whose text isn't prose
+const SKIPPED_ELEMENTS: &str = "code, pre, script, style, svg, .katex, .katex-display";
+
+pub struct SpellChecker {
+    dictionary: Dictionary,
+    allowed: HashSet<String>,
+}
+
+impl SpellChecker {
+    /// Load a Hunspell dictionary, either `dictionary` (the `.dic` file, with its `.aff` file
+    /// alongside) or an installed one for `lang`, along with an optional list of allowed words.
+    pub fn load(dictionary: Option<&Path>, lang: &str, allowlist: Option<&Path>) -> Result<Self> {
+        let dic_path = match dictionary {
+            Some(path) => path.to_path_buf(),
+            None => find_dictionary(lang)?,
+        };
+        let aff_path = dic_path.with_extension("aff");
+        let dic = fs::read_to_string(&dic_path)
+            .context(format!("Failed to read dictionary {}", dic_path.display()))?;
+        let aff = fs::read_to_string(&aff_path)
+            .context(format!("Failed to read dictionary {}", aff_path.display()))?;
+        let dictionary = Dictionary::new(&aff, &dic)
+            .map_err(|e| anyhow!("Failed to parse dictionary {}: {e}", dic_path.display()))?;
+
+        let allowed = match allowlist {
+            Some(path) => fs::read_to_string(path)
+                .context(format!(
+                    "Failed to read allowed words from {}",
+                    path.display()
+                ))?
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_lowercase)
+                .collect(),
+            None => HashSet::new(),
+        };
+
+        Ok(SpellChecker {
+            dictionary,
+            allowed,
+        })
+    }
+
+    /// Words in the text of rendered HTML that aren't in the dictionary, in alphabetical order.
+    pub fn misspellings(&self, html: &str) -> Result<BTreeSet<String>> {
+        let text = prose(html)?;
+        Ok(words(&text)
+            .into_iter()
+            .filter(|word| !self.allowed.contains(&word.to_lowercase()))
+            .filter(|word| !self.dictionary.check(word))
+            .collect())
+    }
+}
+
+// Dictionaries are named like `en_US.dic`, while HTML languages are written `en-US`
+fn find_dictionary(lang: &str) -> Result<PathBuf> {
+    let name = lang.replace('-', "_");
+    for dir in DICTIONARY_DIRS {
+        let path = Path::new(dir).join(format!("{name}.dic"));
+        if path.is_file() {
+            return Ok(path);
+        }
+    }
+    Err(anyhow!(
+        "No {name} dictionary found (install one for Hunspell, or pass --dictionary)"
+    ))
+}
+
+// The text people will read, leaving out code and math
+fn prose(html: &str) -> Result<String> {
+    let html = rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![element!(SKIPPED_ELEMENTS, |el: &mut Element| {
+                el.remove();
+                Ok(())
+            })],
+            ..RewriteStrSettings::new()
+        },
+    )?;
+
+    let mut text = String::new();
+    rewrite_str(
+        &html,
+        RewriteStrSettings {
+            document_content_handlers: vec![doc_text!(|chunk| {
+                text.push_str(chunk.as_str());
+                // Keep words in neighboring elements, like table cells, apart
+                if chunk.last_in_text_node() {
+                    text.push(' ');
+                }
+                Ok(())
+            })],
+            ..RewriteStrSettings::new()
+        },
+    )?;
+
+    Ok(text)
+}
+
+// Runs of letters, allowing apostrophes inside words (like "don't"). Words with digits in
+// them, like "3rd", are left out.
+fn words(text: &str) -> Vec<String> {
+    without_character_references(text)
+        .replace('’', "'")
+        .split(|c: char| !c.is_alphanumeric() && c != '\'')
+        .map(|word| word.trim_matches('\''))
+        .filter(|word| !word.is_empty() && !word.contains(|c: char| c.is_numeric()))
+        .map(str::to_string)
+        .collect()
+}
+
+// Text from the HTML still has escapes like `&amp;`, which aren't words
+fn without_character_references(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        result.push(' ');
+        rest = match after.find(';') {
+            Some(end)
+                if after[..end]
+                    .chars()
+                    .all(|c| c.is_alphanumeric() || c == '#') =>
+            {
+                &after[end + 1..]
+            }
+            _ => after,
+        };
+    }
+    result.push_str(rest);
+    result
+}
@@ -1,18 +1,73 @@
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
-use clap::{CommandFactory, Parser, ValueEnum, parser::ValueSource};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum, parser::ValueSource};
 use serde::Deserialize;
+use syntect::highlighting::ThemeSet;
 
-// Args struct - CLI interface
+use crate::context::RenderLog;
+use crate::fonts::FontSpec;
+use crate::front_matter::{FrontMatter, is_stdin, read_page};
+use crate::include;
+use crate::warnings::Warnings;
+
+// Light enough to print well
+const DEFAULT_HIGHLIGHT_THEME: &str = "InspiredGitHub";
+
+// Config files that are found automatically, in order of preference
+const CONFIG_FILE_NAMES: &[&str] = &["worksheet.yaml", "worksheet.yml", "worksheet.toml"];
+
+// Settings holding paths, which are relative to the config file they're in. `fonts` and
+// `title_page` have paths nested inside them.
+const CONFIG_PATH_KEYS: &[&str] = &[
+    "pages",
+    "stylesheet",
+    "output_file",
+    "answer_key_file",
+    "bubble_sheet_file",
+    "out_dir",
+    "cache_dir",
+    "prepend_pdf",
+    "append_pdf",
+    "roster",
+    "document_template",
+    "question_bank",
+];
+
+// Cli struct - CLI interface
 #[derive(Parser, Debug)]
-#[command(version, about, author)]
+#[command(version, about, author, args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    // Without a subcommand, build
+    #[command(flatten)]
+    pub args: Args,
+}
+
+// Options for building a worksheet, shared by the subcommands that build one
+#[derive(clap::Args, Debug, Clone)]
 pub struct Args {
-    /// Path to config file
+    /// Path to config file (or a directory of them, for the site format). Defaults to the
+    /// nearest worksheet.yaml in this directory or its parents
     pub config: Option<PathBuf>,
 
-    /// Output file
-    #[arg(short, long = "output", value_name = "FILE")]
+    /// Don't look for a worksheet.yaml when no config file is given
+    #[arg(long, conflicts_with = "config")]
+    pub no_config: bool,
+
+    /// Profile from the config file whose settings to use
+    #[arg(long, value_name = "NAME", env = "WORKSHEET_PROFILE")]
+    pub profile: Option<String>,
+
+    /// Only build this target from the config file
+    #[arg(long, value_name = "NAME")]
+    pub target: Option<String>,
+
+    /// Output file, or - for stdout
+    #[arg(short, long = "output", value_name = "FILE", env = "WORKSHEET_OUTPUT")]
     pub output_file: Option<PathBuf>,
 
     /// Output format
@@ -20,7 +75,8 @@ pub struct Args {
         short = 'f',
         long = "format",
         value_name = "FORMAT",
-        default_value = "pdf"
+        default_value = "pdf",
+        env = "WORKSHEET_FORMAT"
     )]
     pub output_format: OutputFormat,
 
@@ -28,9 +84,309 @@ pub struct Args {
     #[arg(short, long, value_name = "FILE", num_args = 1..)]
     pub pages: Vec<PathBuf>,
 
-    /// Stylesheet to use
-    #[arg(short, long, value_name = "FILE")]
-    pub stylesheet: Option<PathBuf>,
+    /// Stylesheets to use, in order (later ones override earlier ones)
+    #[arg(short, long, value_name = "FILE", num_args = 1.., env = "WORKSHEET_STYLESHEET")]
+    pub stylesheet: Vec<PathBuf>,
+
+    /// Built-in theme to style the worksheet with, under any stylesheets
+    #[arg(long, value_name = "NAME", env = "WORKSHEET_THEME")]
+    pub theme: Option<BuiltinTheme>,
+
+    /// Tera template for the HTML document, in place of the built-in one
+    #[arg(long, value_name = "FILE")]
+    pub document_template: Option<PathBuf>,
+
+    /// Also render an answer key with answers revealed
+    #[arg(long, env = "WORKSHEET_ANSWER_KEY")]
+    pub answer_key: bool,
+
+    /// Answer key output file
+    #[arg(long, value_name = "FILE")]
+    pub answer_key_file: Option<PathBuf>,
+
+    /// Put the answer key at the end of the worksheet, after a separator page
+    #[arg(long)]
+    pub combined_answer_key: bool,
+
+    /// Add a link to itself next to each heading in HTML output
+    #[arg(long)]
+    pub heading_anchors: bool,
+
+    /// Start with a contents page listing the h1 and h2 headings
+    #[arg(long)]
+    pub toc: bool,
+
+    /// Write each page to its own HTML file, with an index page linking them
+    #[arg(long)]
+    pub split_pages: bool,
+
+    /// Render each page to its own file in this directory, named after the page
+    #[arg(long, value_name = "DIR")]
+    pub out_dir: Option<PathBuf>,
+
+    /// Minify the HTML before writing it or converting it to PDF
+    #[arg(long)]
+    pub minify: bool,
+
+    /// Also render a bubble answer sheet for the multiple-choice questions
+    #[arg(long)]
+    pub bubble_sheet: bool,
+
+    /// Bubble sheet output file
+    #[arg(long, value_name = "FILE")]
+    pub bubble_sheet_file: Option<PathBuf>,
+
+    /// Text to print diagonally across every page, e.g. "DRAFT"
+    #[arg(long, value_name = "TEXT", env = "WORKSHEET_WATERMARK")]
+    pub watermark: Option<String>,
+
+    /// Embed images and fonts in the HTML so it works as a single file
+    #[arg(long, env = "WORKSHEET_SELF_CONTAINED")]
+    pub self_contained: bool,
+
+    /// Copy images and fonts into this directory next to the HTML output
+    #[arg(long, value_name = "DIR")]
+    pub assets_dir: Option<PathBuf>,
+
+    /// Where downloaded images are kept between builds
+    #[arg(long, value_name = "DIR", env = "WORKSHEET_CACHE_DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Program used to render PDFs
+    #[arg(long, value_name = "BACKEND", env = "WORKSHEET_PDF_BACKEND")]
+    pub pdf_backend: Option<PdfBackendKind>,
+
+    /// Produce PDF/A-2b archival PDFs (weasyprint only)
+    #[arg(long)]
+    pub pdfa: bool,
+
+    /// Arrange PDF pages for printing double-sided and folding into a booklet
+    #[arg(long)]
+    pub booklet: bool,
+
+    /// Print 2 or 4 pages on each PDF sheet
+    #[arg(long, value_name = "N")]
+    pub nup: Option<u8>,
+
+    /// Encrypt PDFs so they need this password to open
+    #[arg(
+        long,
+        value_name = "PASSWORD",
+        env = "WORKSHEET_ENCRYPT_PASSWORD",
+        hide_env_values = true
+    )]
+    pub encrypt_password: Option<String>,
+
+    /// Password that lifts the restrictions on encrypted PDFs (defaults to the open password)
+    #[arg(
+        long,
+        value_name = "PASSWORD",
+        requires = "encrypt_password",
+        env = "WORKSHEET_OWNER_PASSWORD",
+        hide_env_values = true
+    )]
+    pub owner_password: Option<String>,
+
+    /// Something readers of encrypted PDFs may do without the owner password (may be repeated)
+    #[arg(
+        long = "allow",
+        value_name = "PERMISSION",
+        requires = "encrypt_password"
+    )]
+    pub permissions: Vec<Permission>,
+
+    /// Extra argument to pass to the PDF backend (may be repeated)
+    #[arg(long = "pdf-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub pdf_args: Vec<String>,
+
+    /// How to render LaTeX math
+    #[arg(long, value_name = "RENDERER", env = "WORKSHEET_MATH")]
+    pub math: Option<MathRenderer>,
+
+    /// Syntax highlighting theme for code blocks, or "none"
+    #[arg(long, value_name = "THEME", env = "WORKSHEET_HIGHLIGHT_THEME")]
+    pub highlight_theme: Option<String>,
+
+    /// CSV file with one row per student; renders a personalized copy for each
+    #[arg(long, value_name = "FILE")]
+    pub roster: Option<PathBuf>,
+
+    /// Template for each student's output file name, e.g. "{{ student.name }}.pdf"
+    #[arg(long, value_name = "TEMPLATE")]
+    pub roster_output_name: Option<String>,
+
+    /// YAML or TOML file of questions for the `bank()` template function
+    #[arg(long, value_name = "FILE")]
+    pub question_bank: Option<PathBuf>,
+
+    /// Seed for randomized content, to reproduce a previous worksheet
+    #[arg(long, value_name = "SEED", env = "WORKSHEET_SEED")]
+    pub seed: Option<u64>,
+
+    /// Number of shuffled variants (Form A, B, C...) to generate
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(1..=26))]
+    pub variants: Option<u8>,
+
+    /// Open the result in the default viewer after building
+    #[arg(long)]
+    pub open: bool,
+
+    /// Fail instead of warning, e.g. about missing images or stylesheets
+    #[arg(long, env = "WORKSHEET_STRICT")]
+    pub strict: bool,
+
+    /// Render everything and print what would be written, without writing any files
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Write a JSON summary of the build's inputs, outputs, seeds, and warnings
+    #[arg(long, value_name = "FILE")]
+    pub manifest: Option<PathBuf>,
+
+    /// How many files to build at once [default: the number of CPUs]
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        env = "WORKSHEET_JOBS",
+        value_parser = clap::value_parser!(u16).range(1..)
+    )]
+    pub jobs: Option<u16>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Build the worksheet (the default without a subcommand)
+    Build(Args),
+    /// Rebuild whenever the config, pages, or stylesheet change
+    Watch(Args),
+    /// Serve a live-reloading HTML preview
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8000")]
+        address: String,
+
+        #[command(flatten)]
+        args: Args,
+    },
+    /// Report broken links, missing images and includes, empty headings, and malformed blocks
+    Check {
+        /// Also report misspelled words
+        #[arg(long)]
+        spelling: bool,
+
+        /// Hunspell dictionary (.dic file) to spell check with [default: the installed one
+        /// for the document's language]
+        #[arg(long, value_name = "FILE", requires = "spelling")]
+        dictionary: Option<PathBuf>,
+
+        /// File of extra words to accept, one per line [default: allowed-words.txt next to
+        /// the config, if there is one]
+        #[arg(long, value_name = "FILE", requires = "spelling")]
+        allowlist: Option<PathBuf>,
+
+        #[command(flatten)]
+        args: Args,
+    },
+    /// Lay out double-sided flashcards with cut lines
+    Flashcards {
+        /// CSV file (front and back columns) or markdown file (`front: back` lines)
+        cards: PathBuf,
+
+        /// Cards across each page
+        #[arg(long, default_value_t = 2)]
+        columns: usize,
+
+        /// Cards down each page
+        #[arg(long, default_value_t = 4)]
+        rows: usize,
+
+        #[command(flatten)]
+        args: Args,
+    },
+    /// Create a starter config, page, and stylesheet in the current directory
+    Init {
+        /// Kind of worksheet to start from
+        #[arg(long, value_name = "TEMPLATE", default_value = "basic")]
+        template: InitTemplate,
+
+        /// Overwrite existing files
+        #[arg(long)]
+        force: bool,
+    },
+    /// Start a new page from a worksheet template
+    New {
+        /// Kind of worksheet
+        template: NewTemplate,
+
+        /// Markdown file to create [default: <TEMPLATE>.md]
+        path: Option<PathBuf>,
+
+        /// Page title [default: from the template]
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Date shown on the page [default: today]
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+/// Starting points for `init`.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum InitTemplate {
+    /// Questions with answers and a callout
+    Basic,
+    /// An arithmetic drill and word problems
+    Math,
+    /// Multiple choice and matching questions
+    Quiz,
+}
+
+/// Page templates for `new`.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum NewTemplate {
+    /// Multiple choice, matching, and short answer sections
+    Quiz,
+    /// A timed arithmetic drill
+    Drill,
+    /// Hypothesis, procedure, observations, and conclusion
+    LabReport,
+    /// A word list with fill-in-the-blank sentences and handwriting practice
+    Spelling,
+}
+
+impl NewTemplate {
+    pub fn name(self) -> &'static str {
+        match self {
+            NewTemplate::Quiz => "quiz",
+            NewTemplate::Drill => "drill",
+            NewTemplate::LabReport => "lab-report",
+            NewTemplate::Spelling => "spelling",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            NewTemplate::Quiz => "Quiz",
+            NewTemplate::Drill => "Addition Drill",
+            NewTemplate::LabReport => "Lab Report",
+            NewTemplate::Spelling => "Spelling Words",
+        }
+    }
+
+    pub fn markdown(self) -> &'static str {
+        match self {
+            NewTemplate::Quiz => include_str!("new/quiz.md"),
+            NewTemplate::Drill => include_str!("new/drill.md"),
+            NewTemplate::LabReport => include_str!("new/lab-report.md"),
+            NewTemplate::Spelling => include_str!("new/spelling.md"),
+        }
+    }
 }
 
 #[derive(ValueEnum, Clone, Debug, Deserialize)]
@@ -38,24 +394,496 @@ pub struct Args {
 pub enum OutputFormat {
     Pdf,
     Html,
+    Typst,
+    Docx,
+    /// One image per page
+    Png,
+    /// A browsable website of every worksheet config in a directory
+    Site,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PdfBackendKind {
+    #[default]
+    Weasyprint,
+    Wkhtmltopdf,
+    /// Built-in renderer for simple text worksheets (ignores stylesheets)
+    Native,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MathRenderer {
+    /// Typeset HTML (needs KaTeX's stylesheet and fonts)
+    #[default]
+    Katex,
+    /// MathML markup, which the browser or PDF renderer lays out itself
+    Mathml,
+}
+
+/// A theme compiled into the binary, applied before the user stylesheets.
+#[derive(ValueEnum, Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BuiltinTheme {
+    /// Serif text and restrained rules, like a printed textbook
+    Classic,
+    /// Rounded fonts and bright colors for younger students
+    Playful,
+    /// Black on white with thin rules, to save toner
+    MinimalInk,
+    /// Big, high-contrast text with generous spacing
+    LargePrint,
+}
+
+impl BuiltinTheme {
+    pub fn css(self) -> &'static str {
+        match self {
+            BuiltinTheme::Classic => include_str!("themes/classic.css"),
+            BuiltinTheme::Playful => include_str!("themes/playful.css"),
+            BuiltinTheme::MinimalInk => include_str!("themes/minimal-ink.css"),
+            BuiltinTheme::LargePrint => include_str!("themes/large-print.css"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Orientation {
+    Portrait,
+    Landscape,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    Print,
+    /// Copy text and images
+    Copy,
+    /// Change the document
+    Modify,
+    /// Add comments and fill in forms
+    Annotate,
+}
+
+/// Passwords and permissions for encrypted PDFs.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Encryption {
+    /// Needed to open the PDF
+    pub password: String,
+    /// Needed to lift the restrictions, which defaults to `password`
+    pub owner_password: Option<String>,
+    /// What readers may do without the owner password
+    #[serde(default)]
+    pub allow: Vec<Permission>,
+}
+
+/// Automatic numbering for headings and multiple-choice questions.
+///
+/// Formats can use `{1}` through `{6}` for the current number at each heading level, and `{q}`
+/// for the question number.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Numbering {
+    /// Format for each numbered heading level, like `"{2}.{3} "` for level 3
+    #[serde(default)]
+    pub headings: BTreeMap<u8, String>,
+    /// Format for question numbers, like `"{2}.{q}"`
+    pub questions: Option<String>,
+}
+
+/// The direction text runs in, for the HTML `dir` attribute.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextDirection {
+    Ltr,
+    Rtl,
+    Auto,
+}
+
+impl TextDirection {
+    pub fn name(self) -> &'static str {
+        match self {
+            TextDirection::Ltr => "ltr",
+            TextDirection::Rtl => "rtl",
+            TextDirection::Auto => "auto",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SectionTag {
+    #[default]
+    Div,
+    Section,
+}
+
+impl SectionTag {
+    pub fn name(self) -> &'static str {
+        match self {
+            SectionTag::Div => "div",
+            SectionTag::Section => "section",
+        }
+    }
+}
+
+/// A page in the config: just its path, or its path along with settings for it.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PageEntry {
+    Path(PathBuf),
+    Settings(PageConfig),
+}
+
+/// Settings for one page in the config, which take precedence over the page's front matter.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PageConfig {
+    /// The page, or a pattern matching several
+    pub path: PathBuf,
+    pub stylesheet: Option<PathBuf>,
+    pub title: Option<String>,
+    pub orientation: Option<Orientation>,
+    /// How many times to include the page, each with its own randomized content
+    pub variant_count: Option<usize>,
+}
+
+impl PageConfig {
+    /// Apply the settings over a page's front matter.
+    pub fn apply(&self, front_matter: &mut FrontMatter) {
+        if let Some(stylesheet) = &self.stylesheet {
+            front_matter.stylesheet = Some(stylesheet.clone());
+        }
+        if let Some(title) = &self.title {
+            front_matter.title = Some(title.clone());
+        }
+        if let Some(orientation) = self.orientation {
+            front_matter.page.orientation = Some(orientation);
+        }
+    }
+}
+
+/// How the element wrapped around each heading and its content is written.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SectionSettings {
+    pub tag: SectionTag,
+    /// Prepended to the class (or id) made from the heading
+    pub class_prefix: String,
+    /// Identify sections with an `id` instead of a `class`
+    pub use_id: bool,
+    /// Add a `data-level` attribute with the heading level
+    pub data_level: bool,
+}
+
+/// A generated cover page to put before the worksheet.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TitlePage {
+    pub title: Option<String>,
+    pub subtitle: Option<String>,
+    pub class: Option<String>,
+    /// Defaults to today
+    pub date: Option<String>,
+    pub logo: Option<PathBuf>,
+    /// Tera HTML template to use instead of the built-in one
+    pub template: Option<PathBuf>,
+}
+
+/// Paper size, orientation, and margins, which become a CSS `@page` rule.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PageSettings {
+    /// A CSS page size like `a4`, `letter`, or `8.5in 11in`
+    pub size: Option<String>,
+    pub orientation: Option<Orientation>,
+    /// Any CSS `margin` value, like `20mm` or `1in 0.75in`
+    pub margins: Option<String>,
+}
+
+impl PageSettings {
+    /// Fill in anything unset from `defaults`.
+    pub fn or(&self, defaults: &PageSettings) -> PageSettings {
+        PageSettings {
+            size: self.size.clone().or_else(|| defaults.size.clone()),
+            orientation: self.orientation.or(defaults.orientation),
+            margins: self.margins.clone().or_else(|| defaults.margins.clone()),
+        }
+    }
+
+    /// Declarations for an `@page` rule, e.g. `size: a4 landscape; margin: 20mm;`
+    pub fn declarations(&self) -> String {
+        let orientation = match self.orientation {
+            Some(Orientation::Portrait) => Some("portrait"),
+            Some(Orientation::Landscape) => Some("landscape"),
+            None => None,
+        };
+        let size: Vec<&str> = self
+            .size
+            .as_deref()
+            .into_iter()
+            .chain(orientation)
+            .collect();
+
+        let mut declarations = String::new();
+        if !size.is_empty() {
+            declarations.push_str(&format!("size: {}; ", size.join(" ")));
+        }
+        if let Some(margins) = &self.margins {
+            declarations.push_str(&format!("margin: {margins}; "));
+        }
+        declarations
+    }
+}
+
+/// Markdown extensions and rendering options. Anything unset keeps its default.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarkdownSettings {
+    pub tables: Option<bool>,
+    pub strikethrough: Option<bool>,
+    pub tasklists: Option<bool>,
+    pub superscript: Option<bool>,
+    pub footnotes: Option<bool>,
+    pub description_lists: Option<bool>,
+    /// `$...$` and `$$...$$` math
+    pub math: Option<bool>,
+    /// Treat every newline as a line break
+    pub hardbreaks: Option<bool>,
+    /// Pass raw HTML through to the output
+    pub unsafe_html: Option<bool>,
+    /// Curly quotes, en and em dashes from `--` and `---`, and ellipses from `...`
+    pub smart: Option<bool>,
+    /// Emoji shortcodes like `:star:`
+    pub emoji: Option<bool>,
+}
+
+impl MarkdownSettings {
+    /// Fill in anything unset from `defaults`.
+    pub fn or(&self, defaults: &MarkdownSettings) -> MarkdownSettings {
+        MarkdownSettings {
+            tables: self.tables.or(defaults.tables),
+            strikethrough: self.strikethrough.or(defaults.strikethrough),
+            tasklists: self.tasklists.or(defaults.tasklists),
+            superscript: self.superscript.or(defaults.superscript),
+            footnotes: self.footnotes.or(defaults.footnotes),
+            description_lists: self.description_lists.or(defaults.description_lists),
+            math: self.math.or(defaults.math),
+            hardbreaks: self.hardbreaks.or(defaults.hardbreaks),
+            unsafe_html: self.unsafe_html.or(defaults.unsafe_html),
+            smart: self.smart.or(defaults.smart),
+            emoji: self.emoji.or(defaults.emoji),
+        }
+    }
+
+    pub fn comrak_options(&self) -> comrak::Options<'static> {
+        let mut options = comrak::Options::default();
+
+        // Common extensions are on unless turned off
+        options.extension.table = self.tables.unwrap_or(true);
+        options.extension.strikethrough = self.strikethrough.unwrap_or(true);
+        options.extension.tasklist = self.tasklists.unwrap_or(true);
+        options.extension.superscript = self.superscript.unwrap_or(true);
+        options.extension.footnotes = self.footnotes.unwrap_or(true);
+        options.extension.description_lists = self.description_lists.unwrap_or(true);
+        options.extension.math_dollars = self.math.unwrap_or(true);
+        options.extension.shortcodes = self.emoji.unwrap_or(false);
+
+        options.parse.smart = self.smart.unwrap_or(false);
+
+        options.render.unsafe_ = self.unsafe_html.unwrap_or(true);
+        options.render.hardbreaks = self.hardbreaks.unwrap_or(false);
+        options.render.github_pre_lang = true;
+
+        options
+    }
+}
+
+/// Text for the left, center, and right of a running header or footer.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RunningText {
+    pub left: Option<String>,
+    pub center: Option<String>,
+    pub right: Option<String>,
+}
+
+// Extra command-line arguments for each PDF backend
+#[derive(Debug, Deserialize, Default)]
+pub struct PdfBackendArgs {
+    #[serde(default)]
+    pub weasyprint: Vec<String>,
+    #[serde(default)]
+    pub wkhtmltopdf: Vec<String>,
+}
+
+impl PdfBackendArgs {
+    fn for_backend(self, backend: PdfBackendKind) -> Vec<String> {
+        match backend {
+            PdfBackendKind::Weasyprint => self.weasyprint,
+            PdfBackendKind::Wkhtmltopdf => self.wkhtmltopdf,
+            PdfBackendKind::Native => vec![],
+        }
+    }
+}
+
+/// A single path, or a list of them.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum PathList {
+    One(PathBuf),
+    Many(Vec<PathBuf>),
+}
+
+impl PathList {
+    fn into_vec(self) -> Vec<PathBuf> {
+        match self {
+            PathList::One(path) => vec![path],
+            PathList::Many(paths) => paths,
+        }
+    }
 }
 
 // Config struct - File-based configuration
 #[derive(Debug, Deserialize, Default)]
 pub struct Config {
-    pub pages: Vec<PathBuf>,
-    pub stylesheet: Option<PathBuf>,
+    pub pages: Vec<PageEntry>,
+    pub stylesheet: Option<PathList>,
     pub output_file: Option<PathBuf>,
     pub output_format: Option<OutputFormat>,
+    pub answer_key: Option<bool>,
+    pub answer_key_file: Option<PathBuf>,
+    pub combined_answer_key: Option<bool>,
+    pub bubble_sheet: Option<bool>,
+    pub bubble_sheet_file: Option<PathBuf>,
+    pub self_contained: Option<bool>,
+    pub assets_dir: Option<PathBuf>,
+    pub cache_dir: Option<PathBuf>,
+    pub pdf_backend: Option<PdfBackendKind>,
+    #[serde(default)]
+    pub pdf_backend_args: PdfBackendArgs,
+    pub pdfa: Option<bool>,
+    pub prepend_pdf: Option<PathBuf>,
+    pub append_pdf: Option<PathBuf>,
+    pub booklet: Option<bool>,
+    pub nup: Option<u8>,
+    pub encryption: Option<Encryption>,
+    #[serde(default)]
+    pub page: PageSettings,
+    pub watermark: Option<String>,
+    pub title_page: Option<TitlePage>,
+    pub toc: Option<bool>,
+    pub numbering: Option<Numbering>,
+    #[serde(default)]
+    pub markdown: MarkdownSettings,
+    #[serde(default)]
+    pub shortcodes: BTreeMap<String, String>,
+    #[serde(default)]
+    pub sections: SectionSettings,
+    pub heading_anchors: Option<bool>,
+    pub split_pages: Option<bool>,
+    pub out_dir: Option<PathBuf>,
+    pub minify: Option<bool>,
+    pub strict: Option<bool>,
+    #[serde(default)]
+    pub header: RunningText,
+    #[serde(default)]
+    pub footer: RunningText,
+    #[serde(default)]
+    pub variables: BTreeMap<String, serde_yaml::Value>,
+    pub math: Option<MathRenderer>,
+    pub highlight_theme: Option<String>,
+    #[serde(default)]
+    pub fonts: Vec<FontSpec>,
+    #[serde(default)]
+    pub theme: BTreeMap<String, serde_yaml::Value>,
+    pub builtin_theme: Option<BuiltinTheme>,
+    pub document_template: Option<PathBuf>,
+    pub head_html: Option<String>,
+    pub lang: Option<String>,
+    pub dir: Option<TextDirection>,
+    pub roster: Option<PathBuf>,
+    pub roster_output_name: Option<String>,
+    pub seed: Option<u64>,
+    pub variants: Option<u8>,
+    pub question_bank: Option<PathBuf>,
 }
 
 // Options struct - Final resolved configuration
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Options {
     pub pages: Vec<PathBuf>,
-    pub stylesheet: Option<PathBuf>,
+    // Settings for particular pages from the config
+    pub page_settings: BTreeMap<PathBuf, PageConfig>,
+    pub stylesheets: Vec<PathBuf>,
     pub output_file: PathBuf,
     pub output_format: OutputFormat,
+    pub answer_key_file: Option<PathBuf>,
+    pub combined_answer_key: bool,
+    pub bubble_sheet_file: Option<PathBuf>,
+    pub self_contained: bool,
+    // Relative to the output file's directory
+    pub assets_dir: Option<PathBuf>,
+    pub cache_dir: PathBuf,
+    pub pdf_backend: PdfBackendKind,
+    pub pdf_backend_args: Vec<String>,
+    pub pdfa: bool,
+    // PDFs whose pages go before and after each generated PDF's
+    pub prepend_pdf: Option<PathBuf>,
+    pub append_pdf: Option<PathBuf>,
+    pub booklet: bool,
+    // Pages per sheet, if more than one
+    pub nup: Option<u8>,
+    pub encryption: Option<Encryption>,
+    pub page: PageSettings,
+    pub watermark: Option<String>,
+    pub title_page: Option<TitlePage>,
+    pub toc: bool,
+    pub numbering: Option<Numbering>,
+    pub markdown: MarkdownSettings,
+    // Templates for user-defined functions like `{{ blank(width="3cm") }}`
+    pub shortcodes: BTreeMap<String, String>,
+    pub sections: SectionSettings,
+    pub heading_anchors: bool,
+    pub split_pages: bool,
+    // Directory to render each page into separately, if any
+    pub out_dir: Option<PathBuf>,
+    pub minify: bool,
+    // Warnings from the build, which are errors in strict mode
+    pub warnings: Warnings,
+    // What rendering each document involved, for reporting after the build
+    pub render_log: RenderLog,
+    // Render but don't write anything
+    pub dry_run: bool,
+    // Files to build in parallel, or `None` for one per CPU
+    pub jobs: Option<u16>,
+    pub header: RunningText,
+    pub footer: RunningText,
+    pub variables: BTreeMap<String, serde_yaml::Value>,
+    pub math: MathRenderer,
+    // Syntax highlighting theme, if highlighting is enabled
+    pub highlight_theme: Option<String>,
+    pub fonts: Vec<FontSpec>,
+    // CSS custom properties (without the leading `--`) from the theme
+    pub theme: BTreeMap<String, String>,
+    pub builtin_theme: Option<BuiltinTheme>,
+    pub document_template: Option<PathBuf>,
+    // Extra HTML for the document's `<head>`
+    pub head_html: Option<String>,
+    // Language and text direction of the document, for the `<html>` element
+    pub lang: Option<String>,
+    pub dir: Option<TextDirection>,
+    pub roster: Option<PathBuf>,
+    pub roster_output_name: Option<String>,
+    pub seed: u64,
+    // Whether the seed was picked at random, rather than given
+    pub random_seed: bool,
+    pub variants: u8,
+    // Label of the variant being rendered, if there's more than one
+    pub variant: Option<String>,
+    pub question_bank: Option<PathBuf>,
 }
 
 impl Options {
@@ -68,31 +896,78 @@ impl Options {
             .unwrap_or_else(|| Path::new("."));
 
         // Resolve pages (CLI overrides config)
-        let pages = if !args.pages.is_empty() {
+        let page_entries: Vec<(PathBuf, Option<PageConfig>)> = if !args.pages.is_empty() {
             // CLI paths are relative to CWD
-            args.pages
+            args.pages.into_iter().map(|p| (p, None)).collect()
         } else {
             // Config paths are relative to config file
+            let resolve = |p: PathBuf| if is_stdin(&p) { p } else { config_dir.join(p) };
             config
                 .pages
                 .into_iter()
-                .map(|p| config_dir.join(p))
+                .map(|entry| match entry {
+                    PageEntry::Path(path) => (resolve(path), None),
+                    PageEntry::Settings(settings) => (
+                        resolve(settings.path.clone()),
+                        Some(PageConfig {
+                            stylesheet: settings.stylesheet.map(|s| config_dir.join(s)),
+                            ..settings
+                        }),
+                    ),
+                })
                 .collect()
         };
 
+        // Expand patterns, skipping matches that were already included, and repeat pages with a
+        // variant_count
+        let mut pages = vec![];
+        let mut page_settings = BTreeMap::new();
+        let mut seen = std::collections::HashSet::new();
+        for (pattern, settings) in page_entries {
+            let is_pattern = is_page_pattern(&pattern);
+            for page in expand_page_glob(pattern)? {
+                if !seen.insert(page.clone()) && is_pattern {
+                    continue;
+                }
+                let copies = settings.as_ref().and_then(|s| s.variant_count).unwrap_or(1);
+                if copies == 0 {
+                    return Err(anyhow::anyhow!(
+                        "variant_count for {} must be at least 1",
+                        page.display()
+                    ));
+                }
+                pages.extend(std::iter::repeat_n(page.clone(), copies));
+                if let Some(settings) = &settings {
+                    page_settings.insert(page, settings.clone());
+                }
+            }
+        }
+
         if pages.is_empty() {
             return Err(anyhow::anyhow!(
                 "No pages specified. Use --pages or provide a config file."
             ));
         }
 
-        // Resolve stylesheet (CLI overrides config)
-        let stylesheet = args
-            .stylesheet
-            .or_else(|| config.stylesheet.map(|s| config_dir.join(s)));
+        // Resolve stylesheets (CLI overrides config)
+        let stylesheets = if !args.stylesheet.is_empty() {
+            args.stylesheet
+        } else {
+            config
+                .stylesheet
+                .map(PathList::into_vec)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|s| config_dir.join(s))
+                .collect()
+        };
 
-        // Resolve output format (CLI overrides config only if explicitly set)
-        let format_source = Args::command().get_matches().value_source("output_format");
+        // Resolve output format (CLI or environment overrides config only if explicitly set)
+        let matches = Cli::command().get_matches();
+        let matches = matches
+            .subcommand()
+            .map_or(&matches, |(_, matches)| matches);
+        let format_source = matches.value_source("output_format");
         let output_format = if format_source != Some(ValueSource::DefaultValue) {
             args.output_format
         } else {
@@ -105,46 +980,719 @@ impl Options {
             .or_else(|| config.output_file.map(|p| config_dir.join(p)))
             .unwrap_or_else(|| Self::derive_output_file(&pages, &output_format));
 
+        // Resolve answer key file (explicit path implies an answer key is wanted)
+        let explicit_answer_key_file = args
+            .answer_key_file
+            .or_else(|| config.answer_key_file.map(|p| config_dir.join(p)));
+        let answer_key_wanted = args.answer_key || config.answer_key.unwrap_or(false);
+
+        // Resolve bubble sheet file the same way
+        let explicit_bubble_sheet_file = args
+            .bubble_sheet_file
+            .or_else(|| config.bubble_sheet_file.map(|p| config_dir.join(p)));
+        let bubble_sheet_wanted = args.bubble_sheet || config.bubble_sheet.unwrap_or(false);
+
+        let writes_stdout = is_stdout(&output_file);
+        if writes_stdout {
+            // Other outputs are named after the output file, which stdout doesn't have
+            if answer_key_wanted && explicit_answer_key_file.is_none() {
+                return Err(anyhow::anyhow!(
+                    "Give an answer_key_file when writing to stdout"
+                ));
+            }
+            if bubble_sheet_wanted && explicit_bubble_sheet_file.is_none() {
+                return Err(anyhow::anyhow!(
+                    "Give a bubble_sheet_file when writing to stdout"
+                ));
+            }
+            if matches!(output_format, OutputFormat::Png | OutputFormat::Site) {
+                return Err(anyhow::anyhow!(
+                    "PNG and site output can't be written to stdout"
+                ));
+            }
+        }
+        let answer_key_file = explicit_answer_key_file
+            .or_else(|| answer_key_wanted.then(|| Self::derive_answer_key_file(&output_file)));
+        let bubble_sheet_file = explicit_bubble_sheet_file
+            .or_else(|| bubble_sheet_wanted.then(|| with_stem_suffix(&output_file, "bubbles")));
+
+        let split_pages = args.split_pages || config.split_pages.unwrap_or(false);
+        if split_pages && !matches!(output_format, OutputFormat::Html) {
+            return Err(anyhow::anyhow!("split_pages only works with HTML output"));
+        }
+        if split_pages && writes_stdout {
+            return Err(anyhow::anyhow!(
+                "split_pages output can't be written to stdout"
+            ));
+        }
+        let out_dir = args
+            .out_dir
+            .or_else(|| config.out_dir.map(|p| config_dir.join(p)));
+        if out_dir.is_some() && (split_pages || writes_stdout) {
+            return Err(anyhow::anyhow!(
+                "out_dir can't be used with split_pages or stdout output"
+            ));
+        }
+
+        if let Some(numbering) = &config.numbering
+            && let Some(level) = numbering.headings.keys().find(|l| !(1..=6).contains(*l))
+        {
+            return Err(anyhow::anyhow!(
+                "Can't number heading level {level} (expected 1 to 6)"
+            ));
+        }
+
+        if config.sections.use_id && config.sections.class_prefix.is_empty() {
+            // Otherwise they'd clash with the headings' own ids
+            return Err(anyhow::anyhow!(
+                "sections need a class_prefix when use_id is set"
+            ));
+        }
+
+        let roster = args
+            .roster
+            .or_else(|| config.roster.map(|p| config_dir.join(p)));
+        let combined_answer_key =
+            args.combined_answer_key || config.combined_answer_key.unwrap_or(false);
+        if combined_answer_key && roster.is_some() {
+            // Every student's copy would include the answers
+            return Err(anyhow::anyhow!(
+                "combined_answer_key can't be used with a roster"
+            ));
+        }
+        if writes_stdout && roster.is_some() {
+            return Err(anyhow::anyhow!("Roster output can't be written to stdout"));
+        }
+
+        // Resolve PDF backend (CLI overrides config). Config arguments come first so CLI
+        // arguments can override them.
+        let pdf_backend = args.pdf_backend.or(config.pdf_backend).unwrap_or_default();
+        let mut pdf_backend_args = config.pdf_backend_args.for_backend(pdf_backend);
+        pdf_backend_args.extend(args.pdf_args);
+        let pdfa = args.pdfa || config.pdfa.unwrap_or(false);
+        if pdfa && !matches!(pdf_backend, PdfBackendKind::Weasyprint) {
+            return Err(anyhow::anyhow!(
+                "PDF/A output needs the weasyprint PDF backend"
+            ));
+        }
+
+        let booklet = args.booklet || config.booklet.unwrap_or(false);
+        let nup = args.nup.or(config.nup).filter(|&n| n != 1);
+        if nup.is_some_and(|n| n != 2 && n != 4) {
+            return Err(anyhow::anyhow!("nup must be 1, 2, or 4"));
+        }
+        if booklet && nup.is_some() {
+            return Err(anyhow::anyhow!("booklet and nup can't be used together"));
+        }
+
+        // Resolve encryption (CLI overrides config)
+        let encryption = match args.encrypt_password {
+            Some(password) => Some(Encryption {
+                password,
+                owner_password: args.owner_password,
+                allow: args.permissions,
+            }),
+            None => config.encryption,
+        };
+        if let Some(encryption) = &encryption {
+            // PDF/A forbids encryption, so the result wouldn't be valid PDF/A
+            if pdfa {
+                return Err(anyhow::anyhow!("PDF/A output can't be encrypted"));
+            }
+            // Passwords are passed to qpdf one per line
+            let owner_password = encryption.owner_password.as_deref().unwrap_or_default();
+            if encryption.password.contains('\n') || owner_password.contains('\n') {
+                return Err(anyhow::anyhow!("PDF passwords can't contain line breaks"));
+            }
+        }
+
+        // Resolve seed (CLI overrides config, then a fresh random seed)
+        let random_seed = args.seed.or(config.seed).is_none();
+        let seed = args.seed.or(config.seed).unwrap_or_else(rand::random);
+
+        // Resolve syntax highlighting theme (CLI overrides config, then a print-friendly default)
+        let highlight_theme = match args
+            .highlight_theme
+            .or(config.highlight_theme)
+            .unwrap_or_else(|| DEFAULT_HIGHLIGHT_THEME.to_string())
+        {
+            theme if theme == "none" => None,
+            theme => {
+                let themes = ThemeSet::load_defaults().themes;
+                if !themes.contains_key(&theme) {
+                    let names: Vec<_> = themes.keys().collect();
+                    return Err(anyhow::anyhow!(
+                        "Unknown highlight theme {theme:?} (expected \"none\" or one of {names:?})"
+                    ));
+                }
+                Some(theme)
+            }
+        };
+
+        // Flatten the theme into CSS custom properties, e.g. `colors: {accent: red}` to
+        // `--colors-accent: red`
+        let mut theme = BTreeMap::new();
+        for (name, value) in config.theme {
+            theme_properties(&name, value, &mut theme)?;
+        }
+
+        let variants = args.variants.or(config.variants).unwrap_or(1);
+        if !(1..=26).contains(&variants) {
+            return Err(anyhow::anyhow!("variants must be between 1 and 26"));
+        }
+        if writes_stdout && variants > 1 {
+            return Err(anyhow::anyhow!("Only one variant can be written to stdout"));
+        }
+
         Ok(Options {
             pages,
-            stylesheet,
+            page_settings,
+            stylesheets,
             output_file,
             output_format,
+            answer_key_file,
+            combined_answer_key,
+            bubble_sheet_file,
+            self_contained: args.self_contained || config.self_contained.unwrap_or(false),
+            assets_dir: args.assets_dir.or(config.assets_dir),
+            cache_dir: args
+                .cache_dir
+                .or_else(|| config.cache_dir.map(|p| config_dir.join(p)))
+                .unwrap_or_else(|| config_dir.join(".worksheet-cache")),
+            pdf_backend,
+            pdf_backend_args,
+            pdfa,
+            prepend_pdf: config.prepend_pdf.map(|p| config_dir.join(p)),
+            append_pdf: config.append_pdf.map(|p| config_dir.join(p)),
+            booklet,
+            nup,
+            encryption,
+            page: config.page,
+            watermark: args.watermark.or(config.watermark),
+            title_page: config.title_page.map(|title_page| TitlePage {
+                logo: title_page.logo.map(|p| config_dir.join(p)),
+                template: title_page.template.map(|p| config_dir.join(p)),
+                ..title_page
+            }),
+            toc: args.toc || config.toc.unwrap_or(false),
+            numbering: config.numbering,
+            markdown: config.markdown,
+            shortcodes: config.shortcodes,
+            sections: config.sections,
+            heading_anchors: args.heading_anchors || config.heading_anchors.unwrap_or(false),
+            split_pages,
+            out_dir,
+            minify: args.minify || config.minify.unwrap_or(false),
+            warnings: Warnings::new(args.strict || config.strict.unwrap_or(false)),
+            render_log: RenderLog::default(),
+            dry_run: args.dry_run,
+            jobs: args.jobs,
+            header: config.header,
+            footer: config.footer,
+            variables: config.variables,
+            math: args.math.or(config.math).unwrap_or_default(),
+            highlight_theme,
+            fonts: config
+                .fonts
+                .into_iter()
+                .map(|font| match font {
+                    FontSpec::File {
+                        family,
+                        file,
+                        weight,
+                        style,
+                    } => FontSpec::File {
+                        family,
+                        file: config_dir.join(file),
+                        weight,
+                        style,
+                    },
+                    font => font,
+                })
+                .collect(),
+            theme,
+            builtin_theme: args.theme.or(config.builtin_theme),
+            document_template: args
+                .document_template
+                .or_else(|| config.document_template.map(|p| config_dir.join(p))),
+            head_html: config.head_html,
+            lang: config.lang,
+            dir: config.dir,
+            roster,
+            roster_output_name: args.roster_output_name.or(config.roster_output_name),
+            seed,
+            random_seed,
+            variants,
+            variant: None,
+            question_bank: args
+                .question_bank
+                .or_else(|| config.question_bank.map(|p| config_dir.join(p))),
         })
     }
 
+    /// The files rendering reads, apart from images, which are only found by rendering. Pages
+    /// that can't be read are left for the build to report.
+    pub fn input_files(&self) -> Vec<PathBuf> {
+        let mut files = self.pages.clone();
+        files.extend(self.stylesheets.iter().cloned());
+        files.extend(self.document_template.iter().cloned());
+        files.extend(self.question_bank.iter().cloned());
+        files.extend(self.roster.iter().cloned());
+        for font in &self.fonts {
+            if let FontSpec::File { file, .. } = font {
+                files.push(file.clone());
+            }
+        }
+        for page_path in &self.pages {
+            if let Ok((front_matter, markdown)) = read_page(page_path) {
+                files.extend(front_matter.stylesheet);
+                files.extend(include::included_files(&markdown, page_path));
+            }
+        }
+        files
+    }
+
+    /// Options for each variant to render, with their own seed, label, and output files.
+    pub fn variant_options(&self) -> Vec<Options> {
+        if self.variants == 1 {
+            return vec![self.clone()];
+        }
+
+        (0..self.variants)
+            .map(|i| {
+                let label = char::from(b'A' + i).to_string();
+                let suffix = label.to_lowercase();
+                let mut options = self.clone();
+                options.seed = self.seed.wrapping_add(i.into());
+                options.output_file = with_stem_suffix(&self.output_file, &suffix);
+                options.answer_key_file = self
+                    .answer_key_file
+                    .as_ref()
+                    .map(|file| with_stem_suffix(file, &suffix));
+                options.bubble_sheet_file = self
+                    .bubble_sheet_file
+                    .as_ref()
+                    .map(|file| with_stem_suffix(file, &suffix));
+                options
+                    .variables
+                    .insert("variant".to_string(), label.clone().into());
+                options.variant = Some(label);
+                options
+            })
+            .collect()
+    }
+
+    /// Options for rendering each page to its own file, if there's an output directory.
+    ///
+    /// Output files are named after the pages, with answer keys and bubble sheets named after
+    /// those in turn.
+    pub fn page_options(&self) -> Result<Vec<Options>> {
+        let Some(out_dir) = &self.out_dir else {
+            return Ok(vec![self.clone()]);
+        };
+
+        let mut outputs: BTreeMap<PathBuf, &Path> = BTreeMap::new();
+        let mut page_options = vec![];
+        for page in &self.pages {
+            let output_file = out_dir.join(Self::derive_output_file(
+                std::slice::from_ref(page),
+                &self.output_format,
+            ));
+            if let Some(other) = outputs.insert(output_file.clone(), page) {
+                return Err(anyhow::anyhow!(
+                    "{} and {} would both be written to {}",
+                    other.display(),
+                    page.display(),
+                    output_file.display()
+                ));
+            }
+
+            let mut options = self.clone();
+            options.pages = vec![page.clone()];
+            options.answer_key_file = self
+                .answer_key_file
+                .as_ref()
+                .map(|_| Self::derive_answer_key_file(&output_file));
+            options.bubble_sheet_file = self
+                .bubble_sheet_file
+                .as_ref()
+                .map(|_| with_stem_suffix(&output_file, "bubbles"));
+            options.output_file = output_file;
+            page_options.push(options);
+        }
+
+        Ok(page_options)
+    }
+
+    fn derive_answer_key_file(output_file: &Path) -> PathBuf {
+        with_stem_suffix(output_file, "key")
+    }
+
     fn derive_output_file(pages: &[PathBuf], format: &OutputFormat) -> PathBuf {
         // Try to use the first page's stem as the base name
-        if let Some(first_page) = pages.first() {
-            if let Some(stem) = first_page.file_stem() {
-                let ext = match format {
-                    OutputFormat::Pdf => "pdf",
-                    OutputFormat::Html => "html",
-                };
-                return PathBuf::from(format!("{}.{}", stem.to_string_lossy(), ext));
-            }
+        if let Some(first_page) = pages.first().filter(|page| !is_stdin(page))
+            && let Some(stem) = first_page.file_stem()
+        {
+            let ext = match format {
+                OutputFormat::Pdf => "pdf",
+                OutputFormat::Html => "html",
+                OutputFormat::Typst => "typ",
+                OutputFormat::Docx => "docx",
+                OutputFormat::Png => "png",
+                // A directory, not a file
+                OutputFormat::Site => return PathBuf::from("site"),
+            };
+            return PathBuf::from(format!("{}.{}", stem.to_string_lossy(), ext));
         }
 
         // Fallback
         PathBuf::from(match format {
             OutputFormat::Pdf => "output.pdf",
             OutputFormat::Html => "output.html",
+            OutputFormat::Typst => "output.typ",
+            OutputFormat::Docx => "output.docx",
+            OutputFormat::Png => "output.png",
+            OutputFormat::Site => "site",
         })
     }
 
-    // Helper method to load config from file
-    pub fn load_config(path: Option<&Path>) -> Result<Config> {
+    /// Load the one config to use, for commands that only build one thing. A config with
+    /// targets needs `target` to pick one of them.
+    pub fn load_config(
+        path: Option<&Path>,
+        profile: Option<&str>,
+        target: Option<&str>,
+    ) -> Result<Config> {
+        let mut configs = Self::load_configs(path, profile, target)?;
+        if configs.len() > 1 {
+            return Err(anyhow::anyhow!(
+                "The config has several targets, pick one with --target"
+            ));
+        }
+        configs
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("The config's targets list is empty"))
+    }
+
+    /// Load the config for each of the config file's build targets, or just the one config if
+    /// it doesn't have any. Each target's settings are applied over the rest of the config.
+    ///
+    /// With a `target` name, only that target is loaded.
+    pub fn load_configs(
+        path: Option<&Path>,
+        profile: Option<&str>,
+        target: Option<&str>,
+    ) -> Result<Vec<Config>> {
+        let mut value = Self::load_config_value(path, profile)?;
+        let targets = value
+            .as_mapping_mut()
+            .and_then(|config| config.remove("targets"));
+        let Some(targets) = targets else {
+            if let Some(target) = target {
+                return Err(anyhow::anyhow!("No target {target:?} in the config"));
+            }
+            return Ok(vec![
+                serde_yaml::from_value(value).context("Failed to parse config file")?,
+            ]);
+        };
+
+        let serde_yaml::Value::Sequence(targets) = targets else {
+            return Err(anyhow::anyhow!("targets must be a list"));
+        };
+        let mut configs = vec![];
+        for mut settings in targets {
+            let name = settings
+                .as_mapping_mut()
+                .and_then(|settings| settings.remove("name"))
+                .and_then(|name| name.as_str().map(String::from))
+                .ok_or_else(|| anyhow::anyhow!("Every target needs a name"))?;
+            if target.is_some_and(|target| target != name) {
+                continue;
+            }
+            let mut target_value = value.clone();
+            merge_config_values(&mut target_value, settings);
+            configs.push(
+                serde_yaml::from_value(target_value)
+                    .context(format!("Failed to parse config for target {name:?}"))?,
+            );
+        }
+        if let Some(target) = target
+            && configs.is_empty()
+        {
+            return Err(anyhow::anyhow!("No target {target:?} in the config"));
+        }
+        Ok(configs)
+    }
+
+    // The config file's settings, with a profile applied over them
+    fn load_config_value(path: Option<&Path>, profile: Option<&str>) -> Result<serde_yaml::Value> {
         match path {
             Some(config_path) => {
-                let config_file = std::fs::File::open(config_path).context(format!(
-                    "Failed to open config file: {}",
-                    config_path.display()
-                ))?;
-                let config: Config =
-                    serde_yaml::from_reader(config_file).context("Failed to parse config file")?;
-                Ok(config)
+                let mut value = read_config_value(config_path, &mut vec![])?;
+
+                let profiles = value
+                    .as_mapping_mut()
+                    .and_then(|config| config.remove("profiles"));
+                if let Some(profile) = profile {
+                    let overrides = profiles
+                        .as_ref()
+                        .and_then(|profiles| profiles.get(profile))
+                        .ok_or_else(|| {
+                            anyhow::anyhow!("No profile {profile:?} in {}", config_path.display())
+                        })?;
+                    merge_config_values(&mut value, overrides.clone());
+                }
+
+                Ok(value)
             }
-            None => Ok(Config::default()),
+            None => match profile {
+                Some(profile) => Err(anyhow::anyhow!(
+                    "The {profile:?} profile needs a config file"
+                )),
+                None => Ok(serde_yaml::Value::Mapping(serde_yaml::Mapping::new())),
+            },
         }
     }
 }
+
+// Read a YAML or TOML config (chosen by extension), with any config it `extends` merged in
+// underneath it
+fn read_config_value(path: &Path, stack: &mut Vec<PathBuf>) -> Result<serde_yaml::Value> {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if stack.contains(&canonical) {
+        return Err(anyhow::anyhow!("{} extends itself", path.display()));
+    }
+    stack.push(canonical);
+
+    let mut value = parse_config_file(path)?;
+    let extends = value
+        .as_mapping_mut()
+        .and_then(|config| config.remove("extends"));
+    if let Some(extends) = extends {
+        let extends = extends
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("extends must be a path in {}", path.display()))?;
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let mut base = read_config_value(&dir.join(extends), stack)?;
+        // Make the base config's paths relative to this one
+        let base_dir = Path::new(extends).parent().unwrap_or_else(|| Path::new(""));
+        rebase_config_paths(&mut base, base_dir);
+        merge_config_values(&mut base, value);
+        value = base;
+    }
+
+    stack.pop();
+    Ok(value)
+}
+
+// A config file's own settings, as YAML or TOML depending on the extension
+fn parse_config_file(path: &Path) -> Result<serde_yaml::Value> {
+    let contents = std::fs::read_to_string(path)
+        .context(format!("Failed to open config file: {}", path.display()))?;
+    let value = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&contents)
+            .context(format!("Failed to parse config file {}", path.display()))?,
+        _ => serde_yaml::from_str(&contents)
+            .context(format!("Failed to parse config file {}", path.display()))?,
+    };
+    Ok(value)
+}
+
+/// A config file and each config it `extends`, in order.
+///
+/// Configs that can't be read end the list, since they'd already have failed to load.
+pub fn config_files(path: &Path) -> Vec<PathBuf> {
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let mut files = vec![path.to_path_buf()];
+    let mut seen = vec![canonical(path)];
+    let mut path = path.to_path_buf();
+    while let Ok(value) = parse_config_file(&path) {
+        let Some(extends) = value.get("extends").and_then(|extends| extends.as_str()) else {
+            break;
+        };
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        path = dir.join(extends);
+        if seen.contains(&canonical(&path)) {
+            break;
+        }
+        seen.push(canonical(&path));
+        files.push(path.clone());
+    }
+    files
+}
+
+// Put `dir` in front of every path in a config, including its profiles and targets
+fn rebase_config_paths(config: &mut serde_yaml::Value, dir: &Path) {
+    fn rebase(value: &mut serde_yaml::Value, dir: &Path) {
+        match value {
+            // A page with its own settings
+            serde_yaml::Value::Mapping(page) => {
+                for key in ["path", "stylesheet"] {
+                    if let Some(path) = page.get_mut(key) {
+                        rebase(path, dir);
+                    }
+                }
+            }
+            serde_yaml::Value::String(path) if !is_stdin(Path::new(path.as_str())) => {
+                *path = dir.join(&*path).to_string_lossy().into_owned();
+            }
+            serde_yaml::Value::Sequence(paths) => {
+                for path in paths {
+                    rebase(path, dir);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some(config) = config.as_mapping_mut() else {
+        return;
+    };
+    for key in CONFIG_PATH_KEYS {
+        if let Some(value) = config.get_mut(*key) {
+            rebase(value, dir);
+        }
+    }
+    if let Some(title_page) = config.get_mut("title_page") {
+        for key in ["logo", "template"] {
+            if let Some(value) = title_page.get_mut(key) {
+                rebase(value, dir);
+            }
+        }
+    }
+    if let Some(serde_yaml::Value::Sequence(fonts)) = config.get_mut("fonts") {
+        for font in fonts {
+            if let Some(file) = font.get_mut("file") {
+                rebase(file, dir);
+            }
+        }
+    }
+    if let Some(serde_yaml::Value::Mapping(profiles)) = config.get_mut("profiles") {
+        for (_, profile) in profiles.iter_mut() {
+            rebase_config_paths(profile, dir);
+        }
+    }
+    if let Some(serde_yaml::Value::Sequence(targets)) = config.get_mut("targets") {
+        for target in targets {
+            rebase_config_paths(target, dir);
+        }
+    }
+}
+
+// Apply config settings over others. Nested settings like `page` are merged, so overriding one
+// of them keeps the rest.
+fn merge_config_values(base: &mut serde_yaml::Value, overrides: serde_yaml::Value) {
+    match (base, overrides) {
+        (serde_yaml::Value::Mapping(base), serde_yaml::Value::Mapping(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_config_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+// Add the custom properties for a theme value, recursing into nested maps
+fn theme_properties(
+    name: &str,
+    value: serde_yaml::Value,
+    properties: &mut BTreeMap<String, String>,
+) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(anyhow::anyhow!("Invalid theme variable name {name:?}"));
+    }
+    let value = match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                let key = match key {
+                    serde_yaml::Value::String(key) => key,
+                    key => serde_yaml::to_string(&key)?.trim().to_string(),
+                };
+                theme_properties(&format!("{name}-{key}"), value, properties)?;
+            }
+            return Ok(());
+        }
+        serde_yaml::Value::String(value) => value,
+        serde_yaml::Value::Number(value) => value.to_string(),
+        serde_yaml::Value::Bool(value) => value.to_string(),
+        _ => return Err(anyhow::anyhow!("Invalid value for theme variable {name:?}")),
+    };
+    if value.contains([';', '{', '}']) {
+        return Err(anyhow::anyhow!(
+            "Invalid value for theme variable {name:?}: {value:?}"
+        ));
+    }
+    properties.insert(name.to_string(), value);
+    Ok(())
+}
+
+// The files a page glob pattern matches, or just the page if it isn't a pattern. Matches are
+// sorted by their front matter weight, then by path, with unweighted pages last.
+fn expand_page_glob(page: PathBuf) -> Result<Vec<PathBuf>> {
+    if !is_page_pattern(&page) {
+        return Ok(vec![page]);
+    }
+    let pattern = page.to_string_lossy();
+    let matches = glob::glob(&pattern)
+        .context(format!("Invalid page pattern {pattern:?}"))?
+        .collect::<Result<Vec<_>, _>>()
+        .context(format!("Failed to expand page pattern {pattern:?}"))?;
+    if matches.is_empty() {
+        return Err(anyhow::anyhow!("No pages match {pattern:?}"));
+    }
+    let mut weighted = matches
+        .into_iter()
+        .map(|page| Ok((read_page(&page)?.0.weight, page)))
+        .collect::<Result<Vec<_>>>()?;
+    weighted.sort_by(|(a_weight, a), (b_weight, b)| {
+        (a_weight.is_none(), a_weight, a).cmp(&(b_weight.is_none(), b_weight, b))
+    });
+    Ok(weighted.into_iter().map(|(_, page)| page).collect())
+}
+
+// Whether a page path is a glob pattern rather than a single page
+fn is_page_pattern(page: &Path) -> bool {
+    page.to_string_lossy().contains(['*', '?', '['])
+}
+
+/// Find the nearest `worksheet.yaml` (or `.yml` or `.toml`) in the current directory or its
+/// ancestors.
+pub fn discover_config() -> Result<Option<PathBuf>> {
+    let cwd = std::env::current_dir().context("Failed to get the current directory")?;
+    for dir in cwd.ancestors() {
+        for name in CONFIG_FILE_NAMES {
+            let path = dir.join(name);
+            if path.is_file() {
+                return Ok(Some(path));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Whether an output path means standard output, `-`.
+pub fn is_stdout(output_file: &Path) -> bool {
+    output_file == Path::new("-")
+}
+
+/// Append `-suffix` to a path's file stem, e.g. `quiz.pdf` to `quiz-key.pdf`.
+pub fn with_stem_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(ext) => format!("{stem}-{suffix}.{}", ext.to_string_lossy()),
+        None => format!("{stem}-{suffix}"),
+    };
+    path.with_file_name(file_name)
+}
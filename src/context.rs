@@ -0,0 +1,104 @@
+use std::collections::{BTreeSet, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use comrak::plugins::syntect::{SyntectAdapter, SyntectAdapterBuilder};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use crate::config::{MathRenderer, Options};
+use crate::warnings::Warnings;
+
+/// State shared by everything involved in rendering one document.
+///
+/// The student copy and answer key are rendered from the same seed, so any randomized
+/// content matches between them.
+pub struct RenderContext {
+    pub show_answers: bool,
+    pub math: MathRenderer,
+    /// Markdown options for the current page
+    pub markdown: comrak::Options<'static>,
+    /// Syntax highlighter for ordinary code blocks, if highlighting is enabled
+    pub highlighter: Option<SyntectAdapter>,
+    pub rng: StdRng,
+    /// Words from `{{cloze: ...}}` markup on the current page, for its word bank
+    pub cloze_words: Vec<String>,
+    /// Each multiple-choice question so far, in order
+    pub choice_answers: Vec<ChoiceAnswer>,
+    /// Heading ids used so far, so each is unique in the document
+    pub heading_ids: HashSet<String>,
+    /// Extra `<head>` content from the pages' front matter
+    pub head_html: String,
+    /// The first title from the pages' front matter
+    pub title: Option<String>,
+    /// Ids of the questions chosen from the question bank, in order
+    pub bank_questions: Vec<String>,
+    /// Local image files the pages use
+    pub images: BTreeSet<PathBuf>,
+    pub warnings: Warnings,
+    seed: u64,
+    log: RenderLog,
+}
+
+/// What rendering a document involved, for reporting once the build is done.
+///
+/// Clones share the same log, so renders on other threads can be read back afterwards.
+#[derive(Debug, Clone, Default)]
+pub struct RenderLog(Arc<Mutex<Rendered>>);
+
+#[derive(Debug, Clone, Default)]
+pub struct Rendered {
+    /// Whether anything random was rendered, so the seed matters
+    pub randomized: bool,
+    /// Ids of the questions chosen from the question bank. Every render of a document uses the
+    /// same seed, so they all choose the same ones.
+    pub questions: Vec<String>,
+    /// Local image files used by any render
+    pub images: BTreeSet<PathBuf>,
+}
+
+impl RenderLog {
+    pub fn rendered(&self) -> Rendered {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+pub struct ChoiceAnswer {
+    /// How many choices the question has
+    pub choices: usize,
+    /// Letters of the correct choices
+    pub correct: String,
+}
+
+impl RenderContext {
+    pub fn new(options: &Options, show_answers: bool) -> Self {
+        RenderContext {
+            show_answers,
+            math: options.math,
+            markdown: options.markdown.comrak_options(),
+            highlighter: options
+                .highlight_theme
+                .as_deref()
+                .map(|theme| SyntectAdapterBuilder::new().theme(theme).build()),
+            rng: StdRng::seed_from_u64(options.seed),
+            cloze_words: vec![],
+            choice_answers: vec![],
+            heading_ids: HashSet::new(),
+            head_html: String::new(),
+            title: None,
+            bank_questions: vec![],
+            images: BTreeSet::new(),
+            warnings: options.warnings.clone(),
+            seed: options.seed,
+            log: options.render_log.clone(),
+        }
+    }
+
+    /// Add what was rendered to the options' log, once every page is done.
+    pub fn log_render(&self) {
+        let mut rendered = self.log.0.lock().unwrap();
+        rendered.randomized |= self.rng != StdRng::seed_from_u64(self.seed);
+        rendered.questions.clone_from(&self.bank_questions);
+        rendered.images.extend(self.images.iter().cloned());
+    }
+}
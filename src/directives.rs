@@ -0,0 +1,152 @@
+mod admonition;
+mod bingo;
+mod choice;
+mod clock;
+mod coordplane;
+mod crossword;
+mod drill;
+mod fraction_model;
+mod graphviz;
+mod grid;
+mod handwriting;
+mod hundred_chart;
+mod matching;
+mod maze;
+mod mermaid;
+mod money;
+mod shuffle;
+mod sudoku;
+mod times_table;
+mod word_bank;
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::context::RenderContext;
+
+/// Render a fenced code block whose info string names a directive.
+///
+/// Any words after the name in the info string are passed along as `args`. Returns `None` for
+/// ordinary code blocks so they're rendered as usual.
+pub fn render(
+    name: &str,
+    args: &[&str],
+    body: &str,
+    ctx: &mut RenderContext,
+) -> Result<Option<String>> {
+    let html = match name {
+        kind if admonition::KINDS.contains(&kind) => admonition::render(kind, args, body, ctx)?,
+        "bingo" => bingo::render(body, ctx).context("Invalid bingo block")?,
+        "choice" => choice::render(args, body, ctx).context("Invalid choice block")?,
+        "clock" => clock::render(body, ctx).context("Invalid clock block")?,
+        "coordplane" => coordplane::render(body).context("Invalid coordplane block")?,
+        "crossword" => crossword::render(body, ctx).context("Invalid crossword block")?,
+        "dot" => graphviz::render(body).context("Invalid dot block")?,
+        "drill" => drill::render(body, ctx).context("Invalid drill block")?,
+        "fraction-model" => fraction_model::render(body, ctx.show_answers)
+            .context("Invalid fraction-model block")?,
+        "grid" => grid::render(body).context("Invalid grid block")?,
+        "handwriting" => handwriting::render(body).context("Invalid handwriting block")?,
+        "hundredchart" => hundred_chart::render(body, ctx).context("Invalid hundredchart block")?,
+        "matching" => matching::render(body, ctx).context("Invalid matching block")?,
+        "maze" => maze::render(body, ctx).context("Invalid maze block")?,
+        "mermaid" => mermaid::render(body).context("Invalid mermaid block")?,
+        "money" => money::render(body, ctx).context("Invalid money block")?,
+        "shuffle" => shuffle::render(body, ctx)?,
+        "sudoku" => sudoku::render(body, ctx).context("Invalid sudoku block")?,
+        "times-table" => times_table::render(body, ctx).context("Invalid times-table block")?,
+        "wordbank" => word_bank::render(body, ctx),
+        _ => return Ok(None),
+    };
+
+    Ok(Some(html))
+}
+
+/// Whether a fenced code block with `name` in its info string is rendered as a directive.
+pub fn is_directive(name: &str) -> bool {
+    admonition::KINDS.contains(&name) || NAMES.contains(&name)
+}
+
+// Every directive other than the callouts, which are in `admonition::KINDS`
+const NAMES: &[&str] = &[
+    "bingo",
+    "choice",
+    "clock",
+    "coordplane",
+    "crossword",
+    "dot",
+    "drill",
+    "fraction-model",
+    "grid",
+    "handwriting",
+    "hundredchart",
+    "matching",
+    "maze",
+    "mermaid",
+    "money",
+    "shuffle",
+    "sudoku",
+    "times-table",
+    "wordbank",
+];
+
+/// Turn `::: note` ... `:::` containers into fenced blocks, so they're rendered as callouts.
+///
+/// `set_aside` is code that's been replaced by placeholders, which mustn't close the fences.
+pub fn expand_containers(markdown: &str, set_aside: &[String]) -> String {
+    admonition::expand_containers(markdown, set_aside)
+}
+
+/// The title of a callout block, or `None` if `name` isn't a kind of callout.
+pub fn admonition_title(name: &str, args: &[&str]) -> Option<String> {
+    admonition::KINDS
+        .contains(&name)
+        .then(|| admonition::title(name, args))
+}
+
+/// Render inline `{{name: value}}` markup backed by a directive.
+///
+/// Returns `None` for markup that isn't a directive.
+pub fn render_inline(name: &str, value: &str, ctx: &mut RenderContext) -> Result<Option<String>> {
+    let html = match name {
+        "fraction-model" => fraction_model::render_inline(value, ctx.show_answers)
+            .context("Invalid fraction-model markup")?,
+        _ => return Ok(None),
+    };
+
+    Ok(Some(html))
+}
+
+/// Run an external program with `input` on stdin, and return what it writes to stdout.
+fn pipe_through(mut command: Command, name: &str, input: &str) -> Result<String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context(format!("Failed to spawn {name}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+
+    let output = child.wait_with_output().context(format!("{name} failed"))?;
+    if !output.status.success() {
+        return Err(anyhow!("{name} failed"));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Drop any `<?xml ...?>` declaration and doctype so an SVG file can be inlined in HTML.
+fn strip_xml_prolog(svg: &str) -> &str {
+    match svg.find("<svg") {
+        Some(start) => &svg[start..],
+        None => svg,
+    }
+}
+
+/// A point `radius` from the center at `degrees` clockwise from 12 o'clock.
+fn polar(degrees: f64, radius: f64) -> (f64, f64) {
+    let radians = degrees.to_radians();
+    (radians.sin() * radius, -radians.cos() * radius)
+}
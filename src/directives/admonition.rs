@@ -0,0 +1,95 @@
+use anyhow::Result;
+
+use crate::context::RenderContext;
+use crate::html_gen::{escape_html, render_markdown};
+
+pub const KINDS: &[&str] = &["note", "hint", "tip", "warning"];
+
+/// Render a callout box around some markdown, titled with `args` or the kind of callout.
+pub fn render(kind: &str, args: &[&str], body: &str, ctx: &mut RenderContext) -> Result<String> {
+    let content = render_markdown(&expand_containers(body, &[]), ctx)?;
+
+    Ok(format!(
+        "<div class=\"admonition admonition-{kind}\">\n\
+         <p class=\"admonition-title\">{}</p>\n{content}</div>\n",
+        escape_html(&title(kind, args))
+    ))
+}
+
+/// The callout's title from `args`, or the capitalized kind of callout.
+pub fn title(kind: &str, args: &[&str]) -> String {
+    if args.is_empty() {
+        let mut chars = kind.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    } else {
+        args.join(" ")
+    }
+}
+
+/// Turn `::: kind` ... `:::` containers into fenced blocks for the admonition directive.
+///
+/// Only the outermost containers are converted. Nested ones are handled when their parent's
+/// content is rendered. Unclosed containers are left alone. Fences are also kept longer than any
+/// in `set_aside`, code that's been replaced by placeholders and will be put back later.
+pub fn expand_containers(markdown: &str, set_aside: &[String]) -> String {
+    let longest_set_aside = set_aside
+        .iter()
+        .map(|code| longest_tilde_run(code))
+        .max()
+        .unwrap_or(0);
+    let mut result = String::with_capacity(markdown.len());
+    let mut lines = markdown.split_inclusive('\n');
+
+    while let Some(line) = lines.next() {
+        let Some(info) = container_opening(line) else {
+            result.push_str(line);
+            continue;
+        };
+
+        let mut body = String::new();
+        let mut depth = 1;
+        let mut closed = false;
+        for inner in lines.by_ref() {
+            if container_opening(inner).is_some() {
+                depth += 1;
+            } else if inner.trim() == ":::" {
+                depth -= 1;
+                if depth == 0 {
+                    closed = true;
+                    break;
+                }
+            }
+            body.push_str(inner);
+        }
+
+        if !closed {
+            result.push_str(line);
+            result.push_str(&body);
+            continue;
+        }
+        // The fence has to be longer than any tilde run in the content, including the code
+        // that isn't in it yet
+        let longest_run = longest_tilde_run(&body).max(longest_set_aside);
+        let fence = "~".repeat((longest_run + 1).max(3));
+        if !body.ends_with('\n') {
+            body.push('\n');
+        }
+        result.push_str(&format!("{fence}{info}\n{body}{fence}\n"));
+    }
+
+    result
+}
+
+fn longest_tilde_run(text: &str) -> usize {
+    text.split(|c| c != '~').map(str::len).max().unwrap_or(0)
+}
+
+// The info string (kind and title) of a line like `::: note Read carefully`
+fn container_opening(line: &str) -> Option<&str> {
+    let info = line.trim().strip_prefix(":::")?.trim();
+    let kind = info.split_whitespace().next()?;
+    KINDS.contains(&kind).then_some(info)
+}
@@ -0,0 +1,140 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use rand::seq::{IndexedRandom, SliceRandom};
+use serde::Deserialize;
+
+use crate::context::RenderContext;
+use crate::html_gen::escape_html;
+
+// Give up on unique cards after this many duplicates in a row
+const ATTEMPTS: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BingoSpec {
+    /// Words to fill the cards from
+    #[serde(default)]
+    words: Vec<String>,
+    /// Or a range of numbers, as `[min, max]`
+    numbers: Option<[i64; 2]>,
+    #[serde(default = "default_cards")]
+    cards: usize,
+    #[serde(default = "default_size")]
+    size: usize,
+    #[serde(default = "default_free_space")]
+    free_space: bool,
+    /// Cards per printed page, 1 or 2
+    #[serde(default = "default_per_page")]
+    per_page: usize,
+    /// Column headings, one letter per column
+    #[serde(default = "default_title")]
+    title: String,
+}
+
+fn default_cards() -> usize {
+    1
+}
+
+fn default_size() -> usize {
+    5
+}
+
+fn default_free_space() -> bool {
+    true
+}
+
+fn default_per_page() -> usize {
+    1
+}
+
+fn default_title() -> String {
+    "BINGO".to_string()
+}
+
+/// Render unique randomized bingo cards. The answer key starts with a shuffled call list.
+pub fn render(body: &str, ctx: &mut RenderContext) -> Result<String> {
+    let spec: BingoSpec = serde_yaml::from_str(body)?;
+    let pool: Vec<String> = match (spec.numbers, spec.words.is_empty()) {
+        (Some([min, max]), true) => (min..=max).map(|n| n.to_string()).collect(),
+        (None, false) => spec.words.clone(),
+        _ => return Err(anyhow!("Give either words or numbers")),
+    };
+    if !(1..=2).contains(&spec.per_page) {
+        return Err(anyhow!("per_page must be 1 or 2"));
+    }
+    if spec.size == 0 {
+        return Err(anyhow!("size must be at least 1"));
+    }
+    let has_free_space = spec.free_space && spec.size % 2 == 1;
+    let cell_count = spec.size * spec.size - usize::from(has_free_space);
+    if pool.len() < cell_count {
+        return Err(anyhow!(
+            "Need at least {cell_count} words or numbers, but only have {}",
+            pool.len()
+        ));
+    }
+
+    let mut seen: HashSet<Vec<&String>> = HashSet::new();
+    let mut cards: Vec<Vec<&String>> = vec![];
+    let mut duplicates = 0;
+    while cards.len() < spec.cards {
+        let card: Vec<&String> = pool.choose_multiple(&mut ctx.rng, cell_count).collect();
+        let mut key = card.clone();
+        key.sort();
+        if seen.insert(key) {
+            cards.push(card);
+            duplicates = 0;
+        } else {
+            duplicates += 1;
+            if duplicates == ATTEMPTS {
+                return Err(anyhow!("Couldn't make {} unique cards", spec.cards));
+            }
+        }
+    }
+
+    // Shuffled either way, so the worksheet and key draw the same random numbers afterwards
+    let mut calls: Vec<&String> = pool.iter().collect();
+    calls.shuffle(&mut ctx.rng);
+
+    let mut html = String::new();
+    if ctx.show_answers {
+        html.push_str("<div class=\"bingo-calls answer\"><h3>Call List</h3>\n<ol>\n");
+        for call in calls {
+            writeln!(html, "<li>{}</li>", escape_html(call))?;
+        }
+        html.push_str("</ol>\n</div>\n<div class=\"page-break\"></div>\n");
+    }
+
+    let headings: Vec<char> = spec.title.chars().collect();
+    for (index, card) in cards.iter().enumerate() {
+        if index > 0 && index % spec.per_page == 0 {
+            html.push_str("<div class=\"page-break\"></div>\n");
+        }
+        writeln!(html, "<table class=\"bingo bingo-{}-up\">", spec.per_page)?;
+        if headings.len() == spec.size {
+            html.push_str("<tr>");
+            for heading in &headings {
+                write!(html, "<th>{}</th>", escape_html(&heading.to_string()))?;
+            }
+            html.push_str("</tr>\n");
+        }
+        let mut cells = card.iter();
+        for row in 0..spec.size {
+            html.push_str("<tr>");
+            for column in 0..spec.size {
+                let is_center = row == spec.size / 2 && column == spec.size / 2;
+                if has_free_space && is_center {
+                    html.push_str("<td class=\"bingo-free\">FREE</td>");
+                } else {
+                    write!(html, "<td>{}</td>", escape_html(cells.next().unwrap()))?;
+                }
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</table>\n");
+    }
+
+    Ok(html)
+}
@@ -0,0 +1,117 @@
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use comrak::markdown_to_html;
+use rand::seq::SliceRandom;
+
+use crate::context::{ChoiceAnswer, RenderContext};
+
+const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+struct Choice {
+    text: String,
+    correct: bool,
+}
+
+/// Render a multiple-choice question: a stem followed by `- [ ]` choices, with the correct
+/// ones marked `- [x]`.
+///
+/// Choices are shuffled if `shuffle` follows the block name. The correct letters are recorded
+/// in the context so they can be collected for answer sheets.
+pub fn render(args: &[&str], body: &str, ctx: &mut RenderContext) -> Result<String> {
+    let shuffle = match args {
+        [] => false,
+        ["shuffle"] => true,
+        _ => return Err(anyhow!("Unknown choice options {args:?}")),
+    };
+
+    let mut stem = String::new();
+    let mut choices: Vec<Choice> = vec![];
+    for line in body.lines() {
+        let trimmed = line.trim_start();
+        if let Some(text) = trimmed.strip_prefix("- [ ] ") {
+            choices.push(Choice {
+                text: text.to_string(),
+                correct: false,
+            });
+        } else if let Some(text) = trimmed
+            .strip_prefix("- [x] ")
+            .or_else(|| trimmed.strip_prefix("- [X] "))
+        {
+            choices.push(Choice {
+                text: text.to_string(),
+                correct: true,
+            });
+        } else if let Some(choice) = choices.last_mut() {
+            // Continuation of the previous choice
+            choice.text.push('\n');
+            choice.text.push_str(trimmed);
+        } else {
+            stem.push_str(line);
+            stem.push('\n');
+        }
+    }
+    if choices.len() < 2 || choices.len() > LETTERS.len() {
+        return Err(anyhow!("Expected between 2 and 26 choices"));
+    }
+    if !choices.iter().any(|choice| choice.correct) {
+        return Err(anyhow!("No choice is marked correct with - [x]"));
+    }
+
+    if shuffle {
+        choices.shuffle(&mut ctx.rng);
+    }
+
+    let correct: String = LETTERS
+        .chars()
+        .zip(&choices)
+        .filter(|(_, choice)| choice.correct)
+        .map(|(letter, _)| letter)
+        .collect();
+    ctx.choice_answers.push(ChoiceAnswer {
+        choices: choices.len(),
+        correct: correct.clone(),
+    });
+    let number = ctx.choice_answers.len();
+
+    let mut html = format!(
+        "<div class=\"choice-question\">\n<div class=\"choice-stem\">\
+         <span class=\"choice-number\">{number}.</span>\n{}</div>\n<div class=\"choices\">\n",
+        markdown_to_html(&stem, &ctx.markdown)
+    );
+    for (letter, choice) in LETTERS.chars().zip(&choices) {
+        let class = if ctx.show_answers && choice.correct {
+            "choice answer"
+        } else {
+            "choice"
+        };
+        writeln!(
+            html,
+            "<div class=\"{class}\"><span class=\"choice-letter\">{letter}</span> {}</div>",
+            inline_markdown(&choice.text, &ctx.markdown)
+        )?;
+    }
+    html.push_str("</div>\n");
+    if ctx.show_answers {
+        writeln!(
+            html,
+            "<div class=\"choice-key\">Answer: <span class=\"answer\">{correct}</span></div>"
+        )?;
+    }
+    html.push_str("</div>\n");
+
+    Ok(html)
+}
+
+// Render a short piece of markdown without wrapping it in a paragraph
+fn inline_markdown(markdown: &str, options: &comrak::Options) -> String {
+    let html = markdown_to_html(markdown, options);
+    let html = html.trim();
+    match html
+        .strip_prefix("<p>")
+        .and_then(|html| html.strip_suffix("</p>"))
+    {
+        Some(inner) if !inner.contains("<p>") => inner.to_string(),
+        _ => html.to_string(),
+    }
+}
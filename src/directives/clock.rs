@@ -0,0 +1,168 @@
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use rand::Rng;
+use serde::Deserialize;
+
+use super::polar;
+use crate::context::RenderContext;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ClockSpec {
+    /// A time like `3:45`, a list of times, `random`, or `random x 12`
+    time: TimeSpec,
+    /// How many random clocks to draw
+    count: Option<usize>,
+    /// Minute granularity of random times
+    #[serde(default = "default_interval")]
+    interval: u32,
+    #[serde(default)]
+    mode: Mode,
+    #[serde(default = "default_columns")]
+    columns: usize,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum TimeSpec {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Mode {
+    /// Hands are drawn and the student writes the time
+    #[default]
+    Read,
+    /// The time is written and the student draws the hands
+    Draw,
+}
+
+fn default_interval() -> u32 {
+    5
+}
+
+fn default_columns() -> usize {
+    3
+}
+
+pub fn render(body: &str, ctx: &mut RenderContext) -> Result<String> {
+    // Allow the shorthand of just giving the time as the whole block
+    let spec: ClockSpec = if body.contains("time:") {
+        serde_yaml::from_str(body)?
+    } else {
+        ClockSpec {
+            time: TimeSpec::One(body.trim().to_string()),
+            count: None,
+            interval: default_interval(),
+            mode: Mode::default(),
+            columns: default_columns(),
+        }
+    };
+    if spec.interval == 0 || 60 % spec.interval != 0 {
+        return Err(anyhow!("interval must divide 60"));
+    }
+    if spec.columns == 0 {
+        return Err(anyhow!("columns must be at least 1"));
+    }
+
+    let times: Vec<(u32, u32)> = match &spec.time {
+        TimeSpec::One(time) if time.trim().starts_with("random") => {
+            let count = match time.trim()["random".len()..].trim().strip_prefix('x') {
+                Some(count) => count.trim().parse()?,
+                None => spec.count.unwrap_or(1),
+            };
+            (0..count)
+                .map(|_| {
+                    let hour = ctx.rng.random_range(1..=12);
+                    let minute = ctx.rng.random_range(0..60 / spec.interval) * spec.interval;
+                    (hour, minute)
+                })
+                .collect()
+        }
+        TimeSpec::One(time) => vec![parse_time(time)?],
+        TimeSpec::Many(times) => times
+            .iter()
+            .map(|time| parse_time(time))
+            .collect::<Result<_>>()?,
+    };
+
+    let mut html = String::from("<table class=\"clocks\">\n");
+    for row in times.chunks(spec.columns) {
+        html.push_str("<tr>");
+        for &(hour, minute) in row {
+            let show_hands = spec.mode == Mode::Read || ctx.show_answers;
+            let label = match spec.mode {
+                Mode::Read if ctx.show_answers => {
+                    format!("<span class=\"answer\">{hour}:{minute:02}</span>")
+                }
+                Mode::Read => "<span class=\"answer-blank\"></span>".to_string(),
+                Mode::Draw => format!("{hour}:{minute:02}"),
+            };
+            write!(
+                html,
+                "<td class=\"clock\">{}<div class=\"clock-label\">{label}</div></td>",
+                clock_svg(hour, minute, show_hands, spec.mode == Mode::Draw)
+            )?;
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+
+    Ok(html)
+}
+
+fn parse_time(time: &str) -> Result<(u32, u32)> {
+    let (hour, minute) = time
+        .trim()
+        .split_once(':')
+        .ok_or_else(|| anyhow!("Expected a time like 3:45, got {time:?}"))?;
+    let (hour, minute): (u32, u32) = (hour.trim().parse()?, minute.trim().parse()?);
+    if !(1..=12).contains(&hour) || minute >= 60 {
+        return Err(anyhow!("Invalid time {time:?}"));
+    }
+    Ok((hour, minute))
+}
+
+fn clock_svg(hour: u32, minute: u32, show_hands: bool, hands_are_answer: bool) -> String {
+    let mut svg = String::from(
+        "<svg class=\"clock-face\" xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-110 -110 220 220\">\n\
+         <circle r=\"100\" fill=\"none\" stroke=\"#000\" stroke-width=\"3\"/>\n",
+    );
+
+    for tick in 0..60 {
+        let angle = f64::from(tick) * 6.0;
+        let inner = if tick % 5 == 0 { 88.0 } else { 94.0 };
+        let (x1, y1) = polar(angle, inner);
+        let (x2, y2) = polar(angle, 100.0);
+        let width = if tick % 5 == 0 { 2.5 } else { 1.0 };
+        svg.push_str(&format!(
+            "<line x1=\"{x1:.1}\" y1=\"{y1:.1}\" x2=\"{x2:.1}\" y2=\"{y2:.1}\" stroke=\"#000\" stroke-width=\"{width}\"/>\n"
+        ));
+    }
+    for number in 1..=12 {
+        let (x, y) = polar(f64::from(number) * 30.0, 74.0);
+        svg.push_str(&format!(
+            "<text x=\"{x:.1}\" y=\"{y:.1}\" text-anchor=\"middle\" dominant-baseline=\"central\" \
+             font-size=\"18\" font-family=\"sans-serif\">{number}</text>\n"
+        ));
+    }
+
+    if show_hands {
+        let color = if hands_are_answer { "#c0392b" } else { "#000" };
+        let hour_angle = (f64::from(hour % 12) + f64::from(minute) / 60.0) * 30.0;
+        let minute_angle = f64::from(minute) * 6.0;
+        for (angle, length, width) in [(hour_angle, 50.0, 7), (minute_angle, 80.0, 4)] {
+            let (x, y) = polar(angle, length);
+            svg.push_str(&format!(
+                "<line x1=\"0\" y1=\"0\" x2=\"{x:.1}\" y2=\"{y:.1}\" stroke=\"{color}\" \
+                 stroke-width=\"{width}\" stroke-linecap=\"round\"/>\n"
+            ));
+        }
+    }
+    svg.push_str("<circle r=\"4\" fill=\"#000\"/>\n</svg>");
+
+    svg
+}
@@ -0,0 +1,186 @@
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+use crate::html_gen::escape_html;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+struct CoordPlaneSpec {
+    /// Range of the x axis, as `[min, max]`
+    x: [i32; 2],
+    /// Range of the y axis, as `[min, max]`
+    y: [i32; 2],
+    /// Size of one unit, in millimetres
+    unit: f64,
+    grid: bool,
+    /// Label every Nth tick on each axis
+    label_every: i32,
+    points: Vec<Point>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Point {
+    x: f64,
+    y: f64,
+    label: Option<String>,
+}
+
+impl Default for CoordPlaneSpec {
+    fn default() -> Self {
+        CoordPlaneSpec {
+            x: [-10, 10],
+            y: [-10, 10],
+            unit: 6.0,
+            grid: true,
+            label_every: 1,
+            points: vec![],
+        }
+    }
+}
+
+pub fn render(body: &str) -> Result<String> {
+    let spec: CoordPlaneSpec = if body.trim().is_empty() {
+        CoordPlaneSpec::default()
+    } else {
+        serde_yaml::from_str(body)?
+    };
+    let [x_min, x_max] = spec.x;
+    let [y_min, y_max] = spec.y;
+    if x_min >= x_max || y_min >= y_max {
+        return Err(anyhow!("axis ranges must be [min, max] with min < max"));
+    }
+    if spec.unit <= 0.0 || spec.label_every < 1 {
+        return Err(anyhow!("unit and label_every must be positive"));
+    }
+
+    // Leave a unit of margin on each side for arrowheads and labels
+    let unit = spec.unit;
+    let width = f64::from(x_max - x_min + 2) * unit;
+    let height = f64::from(y_max - y_min + 2) * unit;
+    let to_x = |x: f64| (x - f64::from(x_min) + 1.0) * unit;
+    let to_y = |y: f64| (f64::from(y_max) - y + 1.0) * unit;
+    let font_size = unit * 0.5;
+
+    let mut svg = format!(
+        "<svg class=\"coordplane\" xmlns=\"http://www.w3.org/2000/svg\" \
+         viewBox=\"0 0 {width} {height}\" width=\"{width}mm\" height=\"{height}mm\" \
+         font-size=\"{font_size:.2}\" font-family=\"sans-serif\">\n"
+    );
+
+    if spec.grid {
+        svg.push_str("<g stroke=\"#bbb\" stroke-width=\"0.15\">\n");
+        for x in x_min..=x_max {
+            let px = to_x(f64::from(x));
+            writeln!(
+                svg,
+                "<line x1=\"{px:.2}\" y1=\"{:.2}\" x2=\"{px:.2}\" y2=\"{:.2}\"/>",
+                to_y(f64::from(y_max)),
+                to_y(f64::from(y_min))
+            )?;
+        }
+        for y in y_min..=y_max {
+            let py = to_y(f64::from(y));
+            writeln!(
+                svg,
+                "<line x1=\"{:.2}\" y1=\"{py:.2}\" x2=\"{:.2}\" y2=\"{py:.2}\"/>",
+                to_x(f64::from(x_min)),
+                to_x(f64::from(x_max))
+            )?;
+        }
+        svg.push_str("</g>\n");
+    }
+
+    // Axes sit at zero when it's in range, otherwise along the nearest edge
+    let axis_x = to_x(f64::from(0.clamp(x_min, x_max)));
+    let axis_y = to_y(f64::from(0.clamp(y_min, y_max)));
+    let arrow = unit * 0.3;
+    writeln!(
+        svg,
+        "<g stroke=\"#000\" stroke-width=\"0.4\">\n\
+         <line x1=\"{:.2}\" y1=\"{axis_y:.2}\" x2=\"{:.2}\" y2=\"{axis_y:.2}\"/>\n\
+         <line x1=\"{axis_x:.2}\" y1=\"{:.2}\" x2=\"{axis_x:.2}\" y2=\"{:.2}\"/>\n</g>",
+        arrow,
+        width - arrow,
+        arrow,
+        height - arrow,
+    )?;
+    for (tip, base_a, base_b) in [
+        (
+            (width - arrow, axis_y),
+            (width - 2.0 * arrow, axis_y - arrow / 2.0),
+            (width - 2.0 * arrow, axis_y + arrow / 2.0),
+        ),
+        (
+            (arrow, axis_y),
+            (2.0 * arrow, axis_y - arrow / 2.0),
+            (2.0 * arrow, axis_y + arrow / 2.0),
+        ),
+        (
+            (axis_x, arrow),
+            (axis_x - arrow / 2.0, 2.0 * arrow),
+            (axis_x + arrow / 2.0, 2.0 * arrow),
+        ),
+        (
+            (axis_x, height - arrow),
+            (axis_x - arrow / 2.0, height - 2.0 * arrow),
+            (axis_x + arrow / 2.0, height - 2.0 * arrow),
+        ),
+    ] {
+        writeln!(
+            svg,
+            "<polygon points=\"{:.2},{:.2} {:.2},{:.2} {:.2},{:.2}\"/>",
+            tip.0, tip.1, base_a.0, base_a.1, base_b.0, base_b.1
+        )?;
+    }
+    writeln!(
+        svg,
+        "<text x=\"{:.2}\" y=\"{:.2}\" font-style=\"italic\">x</text>\n\
+         <text x=\"{:.2}\" y=\"{:.2}\" font-style=\"italic\">y</text>",
+        width - arrow,
+        axis_y - arrow,
+        axis_x + arrow,
+        arrow + font_size / 2.0
+    )?;
+
+    // Tick labels, skipping the origin so it isn't labelled twice
+    for x in (x_min..=x_max).filter(|x| *x != 0 && x % spec.label_every == 0) {
+        writeln!(
+            svg,
+            "<text x=\"{:.2}\" y=\"{:.2}\" text-anchor=\"middle\">{x}</text>",
+            to_x(f64::from(x)),
+            axis_y + font_size * 1.3
+        )?;
+    }
+    for y in (y_min..=y_max).filter(|y| *y != 0 && y % spec.label_every == 0) {
+        writeln!(
+            svg,
+            "<text x=\"{:.2}\" y=\"{:.2}\" text-anchor=\"end\">{y}</text>",
+            axis_x - font_size * 0.4,
+            to_y(f64::from(y)) + font_size * 0.35
+        )?;
+    }
+
+    for point in &spec.points {
+        let (px, py) = (to_x(point.x), to_y(point.y));
+        writeln!(
+            svg,
+            "<circle cx=\"{px:.2}\" cy=\"{py:.2}\" r=\"{:.2}\"/>",
+            unit * 0.15
+        )?;
+        if let Some(label) = &point.label {
+            writeln!(
+                svg,
+                "<text x=\"{:.2}\" y=\"{:.2}\" font-weight=\"bold\">{}</text>",
+                px + unit * 0.25,
+                py - unit * 0.25,
+                escape_html(label)
+            )?;
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
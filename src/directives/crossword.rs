@@ -0,0 +1,304 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+use crate::context::RenderContext;
+use crate::html_gen::escape_html;
+
+// Layouts are randomized, so try a few and keep the best
+const ATTEMPTS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Across,
+    Down,
+}
+
+impl Direction {
+    fn step(self) -> (i32, i32) {
+        match self {
+            Direction::Across => (1, 0),
+            Direction::Down => (0, 1),
+        }
+    }
+}
+
+struct Entry {
+    word: Vec<char>,
+    clue: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Placement {
+    entry: usize,
+    x: i32,
+    y: i32,
+    direction: Direction,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    letter: char,
+    across: bool,
+    down: bool,
+}
+
+impl Cell {
+    fn is_used(&self, direction: Direction) -> bool {
+        match direction {
+            Direction::Across => self.across,
+            Direction::Down => self.down,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Grid {
+    cells: HashMap<(i32, i32), Cell>,
+    placements: Vec<Placement>,
+}
+
+impl Grid {
+    /// The number of crossings if the word fits at this position, or `None` if it doesn't.
+    fn fit(&self, word: &[char], x: i32, y: i32, direction: Direction) -> Option<usize> {
+        let (dx, dy) = direction.step();
+        let length = word.len() as i32;
+        if self.cells.contains_key(&(x - dx, y - dy))
+            || self.cells.contains_key(&(x + dx * length, y + dy * length))
+        {
+            return None;
+        }
+
+        let mut crossings = 0;
+        for (i, &letter) in word.iter().enumerate() {
+            let position = (x + dx * i as i32, y + dy * i as i32);
+            match self.cells.get(&position) {
+                Some(cell) if cell.letter == letter && !cell.is_used(direction) => crossings += 1,
+                Some(_) => return None,
+                None => {
+                    // New letters can't touch other words side-on
+                    let neighbors = [
+                        (position.0 + dy, position.1 + dx),
+                        (position.0 - dy, position.1 - dx),
+                    ];
+                    if neighbors.iter().any(|p| self.cells.contains_key(p)) {
+                        return None;
+                    }
+                }
+            }
+        }
+        Some(crossings)
+    }
+
+    fn place(&mut self, entry: usize, word: &[char], x: i32, y: i32, direction: Direction) {
+        let (dx, dy) = direction.step();
+        for (i, &letter) in word.iter().enumerate() {
+            let cell = self
+                .cells
+                .entry((x + dx * i as i32, y + dy * i as i32))
+                .or_insert(Cell {
+                    letter,
+                    across: false,
+                    down: false,
+                });
+            match direction {
+                Direction::Across => cell.across = true,
+                Direction::Down => cell.down = true,
+            }
+        }
+        self.placements.push(Placement {
+            entry,
+            x,
+            y,
+            direction,
+        });
+    }
+
+    fn bounds(&self) -> (i32, i32, i32, i32) {
+        let xs = self.cells.keys().map(|&(x, _)| x);
+        let ys = self.cells.keys().map(|&(_, y)| y);
+        (
+            xs.clone().min().unwrap_or(0),
+            ys.clone().min().unwrap_or(0),
+            xs.max().unwrap_or(0),
+            ys.max().unwrap_or(0),
+        )
+    }
+
+    fn area(&self) -> i32 {
+        let (min_x, min_y, max_x, max_y) = self.bounds();
+        (max_x - min_x + 1) * (max_y - min_y + 1)
+    }
+}
+
+pub fn render(body: &str, ctx: &mut RenderContext) -> Result<String> {
+    let entries = parse_entries(body)?;
+    if entries.is_empty() {
+        return Err(anyhow!("No words given"));
+    }
+
+    let grid = (0..ATTEMPTS)
+        .map(|attempt| {
+            let mut order: Vec<usize> = (0..entries.len()).collect();
+            order.shuffle(&mut ctx.rng);
+            // Long words first gives the most compact grids, but mix it up on later attempts
+            if attempt == 0 {
+                order.sort_by_key(|&i| std::cmp::Reverse(entries[i].word.len()));
+            }
+            layout(&entries, &order, &mut ctx.rng)
+        })
+        .max_by_key(|grid| (grid.placements.len(), std::cmp::Reverse(grid.area())))
+        .expect("At least one attempt");
+
+    for (i, entry) in entries.iter().enumerate() {
+        if !grid.placements.iter().any(|placement| placement.entry == i) {
+            let word: String = entry.word.iter().collect();
+            ctx.warnings.warn(
+                format!("Couldn't fit {word} into the crossword"),
+                "leaving it out",
+            )?;
+        }
+    }
+
+    render_grid(&grid, &entries, ctx.show_answers)
+}
+
+/// Parse `WORD: clue` lines.
+fn parse_entries(body: &str) -> Result<Vec<Entry>> {
+    body.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (word, clue) = line
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Expected `WORD: clue`, got {line:?}"))?;
+            let word: Vec<char> = word
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_uppercase)
+                .collect();
+            if word.is_empty() {
+                return Err(anyhow!("Missing word in {line:?}"));
+            }
+            Ok(Entry {
+                word,
+                clue: clue.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn layout(entries: &[Entry], order: &[usize], rng: &mut impl Rng) -> Grid {
+    let mut grid = Grid::default();
+    let Some((&first, rest)) = order.split_first() else {
+        return grid;
+    };
+    grid.place(first, &entries[first].word, 0, 0, Direction::Across);
+
+    for &entry in rest {
+        let word = &entries[entry].word;
+        let mut candidates = vec![];
+        for (&(cell_x, cell_y), cell) in &grid.cells {
+            for (i, _) in word.iter().enumerate().filter(|&(_, &c)| c == cell.letter) {
+                for direction in [Direction::Across, Direction::Down] {
+                    let (dx, dy) = direction.step();
+                    let (x, y) = (cell_x - dx * i as i32, cell_y - dy * i as i32);
+                    if let Some(crossings) = grid.fit(word, x, y, direction) {
+                        candidates.push((crossings, x, y, direction));
+                    }
+                }
+            }
+        }
+
+        // HashMap iteration order isn't stable, so sort before breaking ties randomly
+        candidates.sort_by_key(|&(crossings, x, y, direction)| {
+            (crossings, x, y, direction == Direction::Down)
+        });
+        candidates.dedup_by_key(|&mut (_, x, y, direction)| (x, y, direction));
+        candidates.shuffle(rng);
+        let best = candidates
+            .into_iter()
+            .max_by_key(|&(crossings, x, y, direction)| {
+                let mut trial = grid.clone();
+                trial.place(entry, word, x, y, direction);
+                (crossings, std::cmp::Reverse(trial.area()))
+            });
+        if let Some((_, x, y, direction)) = best {
+            grid.place(entry, word, x, y, direction);
+        }
+    }
+
+    grid
+}
+
+fn render_grid(grid: &Grid, entries: &[Entry], show_answers: bool) -> Result<String> {
+    let (min_x, min_y, max_x, max_y) = grid.bounds();
+
+    // Number word starts in reading order; a cell starting both directions shares a number
+    let mut starts: Vec<(i32, i32)> = grid.placements.iter().map(|p| (p.y, p.x)).collect();
+    starts.sort();
+    starts.dedup();
+    let numbers: HashMap<(i32, i32), usize> = starts
+        .iter()
+        .enumerate()
+        .map(|(i, &(y, x))| ((x, y), i + 1))
+        .collect();
+
+    let mut html = String::from("<div class=\"crossword\">\n<table class=\"crossword-grid\">\n");
+    for y in min_y..=max_y {
+        html.push_str("<tr>");
+        for x in min_x..=max_x {
+            match grid.cells.get(&(x, y)) {
+                Some(cell) => {
+                    let number = numbers
+                        .get(&(x, y))
+                        .map(|n| format!("<span class=\"crossword-number\">{n}</span>"))
+                        .unwrap_or_default();
+                    let letter = if show_answers {
+                        format!("<span class=\"answer\">{}</span>", cell.letter)
+                    } else {
+                        String::new()
+                    };
+                    write!(html, "<td class=\"crossword-cell\">{number}{letter}</td>")?;
+                }
+                None => html.push_str("<td class=\"crossword-empty\"></td>"),
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n<div class=\"crossword-clues\">\n");
+
+    for (direction, title) in [(Direction::Across, "Across"), (Direction::Down, "Down")] {
+        let mut clues: Vec<(usize, &Entry)> = grid
+            .placements
+            .iter()
+            .filter(|placement| placement.direction == direction)
+            .map(|placement| {
+                (
+                    numbers[&(placement.x, placement.y)],
+                    &entries[placement.entry],
+                )
+            })
+            .collect();
+        clues.sort_by_key(|&(number, _)| number);
+
+        write!(
+            html,
+            "<div class=\"crossword-{}\">\n<h4>{title}</h4>\n<ol>\n",
+            title.to_lowercase()
+        )?;
+        for (number, entry) in clues {
+            writeln!(
+                html,
+                "<li value=\"{number}\">{}</li>",
+                escape_html(&entry.clue)
+            )?;
+        }
+        html.push_str("</ol>\n</div>\n");
+    }
+    html.push_str("</div>\n</div>\n");
+
+    Ok(html)
+}
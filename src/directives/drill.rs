@@ -0,0 +1,151 @@
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::context::RenderContext;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DrillSpec {
+    operation: Operation,
+    #[serde(default = "default_range")]
+    left: [i64; 2],
+    #[serde(default = "default_range")]
+    right: [i64; 2],
+    #[serde(default = "default_count")]
+    count: usize,
+    #[serde(default = "default_columns")]
+    columns: usize,
+    /// Allow subtraction problems with negative answers
+    #[serde(default)]
+    allow_negative: bool,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Operation {
+    Addition,
+    Subtraction,
+    Multiplication,
+    Division,
+}
+
+impl Operation {
+    fn symbol(self) -> &'static str {
+        match self {
+            Operation::Addition => "+",
+            Operation::Subtraction => "−",
+            Operation::Multiplication => "×",
+            Operation::Division => "÷",
+        }
+    }
+}
+
+struct Problem {
+    left: i64,
+    right: i64,
+    answer: i64,
+}
+
+fn default_range() -> [i64; 2] {
+    [0, 10]
+}
+
+fn default_count() -> usize {
+    20
+}
+
+fn default_columns() -> usize {
+    4
+}
+
+pub fn render(body: &str, ctx: &mut RenderContext) -> Result<String> {
+    let spec: DrillSpec = serde_yaml::from_str(body)?;
+    for [min, max] in [spec.left, spec.right] {
+        if min > max {
+            return Err(anyhow!("Invalid operand range [{min}, {max}]"));
+        }
+    }
+    if spec.columns == 0 {
+        return Err(anyhow!("columns must be at least 1"));
+    }
+
+    let problems = (0..spec.count)
+        .map(|_| generate_problem(&spec, &mut ctx.rng))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut html = String::from("<table class=\"drill\">\n");
+    for (row_index, row) in problems.chunks(spec.columns).enumerate() {
+        html.push_str("<tr>");
+        for (column_index, problem) in row.iter().enumerate() {
+            let number = row_index * spec.columns + column_index + 1;
+            let answer = if ctx.show_answers {
+                format!("<span class=\"answer\">{}</span>", problem.answer)
+            } else {
+                String::new()
+            };
+            write!(
+                html,
+                "<td class=\"drill-problem\"><span class=\"drill-number\">{number}.</span>\
+                 <span class=\"drill-left\">{}</span>\
+                 <span class=\"drill-right\">{} {}</span>\
+                 <span class=\"drill-answer\">{answer}</span></td>",
+                problem.left,
+                spec.operation.symbol(),
+                problem.right,
+            )?;
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+
+    Ok(html)
+}
+
+fn generate_problem(spec: &DrillSpec, rng: &mut impl Rng) -> Result<Problem> {
+    let mut left = rng.random_range(spec.left[0]..=spec.left[1]);
+    let mut right = rng.random_range(spec.right[0]..=spec.right[1]);
+
+    let problem = match spec.operation {
+        Operation::Addition => Problem {
+            left,
+            right,
+            answer: left + right,
+        },
+        Operation::Subtraction => {
+            if left < right && !spec.allow_negative {
+                std::mem::swap(&mut left, &mut right);
+            }
+            Problem {
+                left,
+                right,
+                answer: left - right,
+            }
+        }
+        Operation::Multiplication => Problem {
+            left,
+            right,
+            answer: left * right,
+        },
+        Operation::Division => {
+            // Build the dividend from the quotient so every answer is a whole number
+            if spec.right == [0, 0] {
+                return Err(anyhow!(
+                    "Division needs a divisor range with nonzero values"
+                ));
+            }
+            while right == 0 {
+                right = rng.random_range(spec.right[0]..=spec.right[1]);
+            }
+            Problem {
+                left: left * right,
+                right,
+                answer: left,
+            }
+        }
+    };
+
+    Ok(problem)
+}
@@ -0,0 +1,189 @@
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+
+use super::polar;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Circle,
+    Bar,
+    NumberLine,
+}
+
+/// A model like `3/8 circle`, or `3/8 circle shade` for a "shade the fraction" exercise.
+struct Model {
+    numerator: u32,
+    denominator: u32,
+    kind: Kind,
+    /// Leave the model blank for the student to fill in, and show it in the answer key
+    exercise: bool,
+}
+
+// Fill for shaded parts, and for parts shaded in the answer key
+const FILL: &str = "#9ecae1";
+const ANSWER_FILL: &str = "#f5b7b1";
+
+/// Render a fenced block with one model per line.
+pub fn render(body: &str, show_answers: bool) -> Result<String> {
+    let mut html = String::from("<div class=\"fraction-models\">\n");
+    for line in body.lines().filter(|line| !line.trim().is_empty()) {
+        html.push_str(&render_inline(line, show_answers)?);
+        html.push('\n');
+    }
+    html.push_str("</div>\n");
+    Ok(html)
+}
+
+/// Render a single model, for inline `{{fraction-model: 3/8 circle}}` markup.
+pub fn render_inline(spec: &str, show_answers: bool) -> Result<String> {
+    let model = parse_model(spec)?;
+    let fill = match (model.exercise, show_answers) {
+        (false, _) => Some(FILL),
+        (true, true) => Some(ANSWER_FILL),
+        (true, false) => None,
+    };
+    // Improper fractions get one model per whole
+    let wholes = model.numerator.div_ceil(model.denominator).max(1);
+
+    let svg = match model.kind {
+        Kind::Circle => (0..wholes)
+            .map(|whole| circle_svg(&model, whole, fill))
+            .collect::<Result<String>>()?,
+        Kind::Bar => (0..wholes)
+            .map(|whole| bar_svg(&model, whole, fill))
+            .collect::<Result<String>>()?,
+        Kind::NumberLine => number_line_svg(&model, wholes, fill.is_some())?,
+    };
+    // Keep it on one line so it can't break up a paragraph
+    Ok(format!(
+        "<span class=\"fraction-model\">{}</span>",
+        svg.replace('\n', "")
+    ))
+}
+
+fn parse_model(spec: &str) -> Result<Model> {
+    let mut words = spec.split_whitespace();
+    let fraction = words
+        .next()
+        .ok_or_else(|| anyhow!("Expected a fraction like 3/8"))?;
+    let (numerator, denominator) = fraction
+        .split_once('/')
+        .ok_or_else(|| anyhow!("Expected a fraction like 3/8, got {fraction:?}"))?;
+    let (numerator, denominator): (u32, u32) = (numerator.parse()?, denominator.parse()?);
+    if denominator == 0 || denominator > 100 {
+        return Err(anyhow!("Denominator must be between 1 and 100"));
+    }
+
+    let mut model = Model {
+        numerator,
+        denominator,
+        kind: Kind::Circle,
+        exercise: false,
+    };
+    for word in words {
+        match word {
+            "circle" => model.kind = Kind::Circle,
+            "bar" => model.kind = Kind::Bar,
+            "numberline" | "number-line" => model.kind = Kind::NumberLine,
+            "shade" => model.exercise = true,
+            _ => return Err(anyhow!("Unknown fraction model option {word:?}")),
+        }
+    }
+    Ok(model)
+}
+
+// Whether the `part`th part of the `whole`th model is shaded
+fn is_shaded(model: &Model, whole: u32, part: u32) -> bool {
+    whole * model.denominator + part < model.numerator
+}
+
+fn circle_svg(model: &Model, whole: u32, fill: Option<&str>) -> Result<String> {
+    let mut svg = String::from(
+        "<svg class=\"fraction-circle\" xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-52 -52 104 104\">\n",
+    );
+    let radius = 50.0;
+    for part in 0..model.denominator {
+        let color = fill
+            .filter(|_| is_shaded(model, whole, part))
+            .unwrap_or("#fff");
+        if model.denominator == 1 {
+            writeln!(svg, "<circle r=\"{radius}\" fill=\"{color}\"/>")?;
+            continue;
+        }
+        let sweep = 360.0 / f64::from(model.denominator);
+        let (x1, y1) = polar(f64::from(part) * sweep, radius);
+        let (x2, y2) = polar(f64::from(part + 1) * sweep, radius);
+        let large_arc = u8::from(sweep > 180.0);
+        writeln!(
+            svg,
+            "<path d=\"M0,0 L{x1:.2},{y1:.2} A{radius},{radius} 0 {large_arc} 1 {x2:.2},{y2:.2} Z\" \
+             fill=\"{color}\" stroke=\"#000\" stroke-width=\"1\"/>"
+        )?;
+    }
+    svg.push_str("<circle r=\"50\" fill=\"none\" stroke=\"#000\" stroke-width=\"2\"/>\n</svg>");
+    Ok(svg)
+}
+
+fn bar_svg(model: &Model, whole: u32, fill: Option<&str>) -> Result<String> {
+    let mut svg = String::from(
+        "<svg class=\"fraction-bar\" xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1 -1 202 42\">\n",
+    );
+    let width = 200.0 / f64::from(model.denominator);
+    for part in 0..model.denominator {
+        let color = fill
+            .filter(|_| is_shaded(model, whole, part))
+            .unwrap_or("#fff");
+        writeln!(
+            svg,
+            "<rect x=\"{:.2}\" y=\"0\" width=\"{width:.2}\" height=\"40\" fill=\"{color}\" \
+             stroke=\"#000\" stroke-width=\"1\"/>",
+            f64::from(part) * width
+        )?;
+    }
+    svg.push_str("<rect width=\"200\" height=\"40\" fill=\"none\" stroke=\"#000\" stroke-width=\"2\"/>\n</svg>");
+    Ok(svg)
+}
+
+fn number_line_svg(model: &Model, wholes: u32, show_point: bool) -> Result<String> {
+    let length = 200.0 * f64::from(wholes);
+    let mut svg = format!(
+        "<svg class=\"fraction-number-line\" xmlns=\"http://www.w3.org/2000/svg\" \
+         viewBox=\"-15 -15 {} 50\" font-size=\"11\" font-family=\"sans-serif\">\n\
+         <line x1=\"0\" y1=\"0\" x2=\"{length}\" y2=\"0\" stroke=\"#000\" stroke-width=\"1.5\"/>\n",
+        length + 30.0
+    );
+    let ticks = wholes * model.denominator;
+    let spacing = length / f64::from(ticks);
+    for tick in 0..=ticks {
+        let x = f64::from(tick) * spacing;
+        let is_whole = tick % model.denominator == 0;
+        let half_height = if is_whole { 8.0 } else { 5.0 };
+        writeln!(
+            svg,
+            "<line x1=\"{x:.2}\" y1=\"{}\" x2=\"{x:.2}\" y2=\"{half_height}\" stroke=\"#000\"/>",
+            -half_height
+        )?;
+        if is_whole {
+            writeln!(
+                svg,
+                "<text x=\"{x:.2}\" y=\"22\" text-anchor=\"middle\">{}</text>",
+                tick / model.denominator
+            )?;
+        }
+    }
+    if show_point {
+        let color = if model.exercise { "#c0392b" } else { "#000" };
+        writeln!(
+            svg,
+            "<circle cx=\"{:.2}\" cy=\"0\" r=\"4\" fill=\"{color}\"/>\n\
+             <text x=\"{:.2}\" y=\"-11\" text-anchor=\"middle\" fill=\"{color}\">{}/{}</text>",
+            f64::from(model.numerator) * spacing,
+            f64::from(model.numerator) * spacing,
+            model.numerator,
+            model.denominator
+        )?;
+    }
+    svg.push_str("</svg>");
+    Ok(svg)
+}
@@ -0,0 +1,18 @@
+use std::process::Command;
+
+use anyhow::Result;
+
+use super::{pipe_through, strip_xml_prolog};
+use crate::pdf_gen::check_executable;
+
+/// Render a Graphviz DOT graph to inline SVG with `dot`.
+pub fn render(body: &str) -> Result<String> {
+    check_executable("dot")?;
+    let mut command = Command::new("dot");
+    command.arg("-Tsvg");
+    let svg = pipe_through(command, "dot", body)?;
+    Ok(format!(
+        "<div class=\"graphviz-diagram\">\n{}\n</div>\n",
+        strip_xml_prolog(&svg)
+    ))
+}
@@ -0,0 +1,131 @@
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+use crate::html_gen::escape_attribute;
+
+// All sizes are in millimetres
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+struct GridSpec {
+    width: f64,
+    height: f64,
+    cell: f64,
+    line_weight: f64,
+    /// Draw every Nth line heavier (0 to disable)
+    bold_every: usize,
+    /// Triangular grid for isometric drawing
+    isometric: bool,
+    color: String,
+}
+
+impl Default for GridSpec {
+    fn default() -> Self {
+        GridSpec {
+            width: 180.0,
+            height: 100.0,
+            cell: 5.0,
+            line_weight: 0.15,
+            bold_every: 0,
+            isometric: false,
+            color: "#999".to_string(),
+        }
+    }
+}
+
+impl GridSpec {
+    fn stroke_width(&self, index: usize) -> f64 {
+        if self.bold_every > 0 && index.is_multiple_of(self.bold_every) {
+            self.line_weight * 3.0
+        } else {
+            self.line_weight
+        }
+    }
+}
+
+pub fn render(body: &str) -> Result<String> {
+    let spec: GridSpec = if body.trim().is_empty() {
+        GridSpec::default()
+    } else {
+        serde_yaml::from_str(body)?
+    };
+    if spec.cell <= 0.0 || spec.width <= 0.0 || spec.height <= 0.0 {
+        return Err(anyhow!("width, height, and cell must be positive"));
+    }
+
+    // The root <svg> clips to its viewport, so lines can safely overshoot the edges
+    let mut svg = format!(
+        "<svg class=\"grid\" xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {w} {h}\" \
+         width=\"{w}mm\" height=\"{h}mm\">\n<g stroke=\"{color}\">\n",
+        w = spec.width,
+        h = spec.height,
+        color = escape_attribute(&spec.color),
+    );
+
+    if spec.isometric {
+        render_isometric(&spec, &mut svg)?;
+    } else {
+        render_square(&spec, &mut svg)?;
+    }
+
+    svg.push_str("</g>\n</svg>\n");
+    Ok(format!("<div class=\"grid-block\">\n{svg}</div>\n"))
+}
+
+fn render_square(spec: &GridSpec, svg: &mut String) -> Result<()> {
+    let columns = (spec.width / spec.cell).floor() as usize;
+    let rows = (spec.height / spec.cell).floor() as usize;
+    for i in 0..=columns {
+        let x = i as f64 * spec.cell;
+        writeln!(
+            svg,
+            "<line x1=\"{x:.3}\" y1=\"0\" x2=\"{x:.3}\" y2=\"{}\" stroke-width=\"{}\"/>",
+            spec.height,
+            spec.stroke_width(i)
+        )?;
+    }
+    for i in 0..=rows {
+        let y = i as f64 * spec.cell;
+        writeln!(
+            svg,
+            "<line x1=\"0\" y1=\"{y:.3}\" x2=\"{}\" y2=\"{y:.3}\" stroke-width=\"{}\"/>",
+            spec.width,
+            spec.stroke_width(i)
+        )?;
+    }
+    Ok(())
+}
+
+/// Horizontal lines plus two families of 60° diagonals, forming equilateral triangles.
+fn render_isometric(spec: &GridSpec, svg: &mut String) -> Result<()> {
+    let row_height = spec.cell * 3f64.sqrt() / 2.0;
+    let rows = (spec.height / row_height).floor() as usize;
+    for i in 0..=rows {
+        let y = i as f64 * row_height;
+        writeln!(
+            svg,
+            "<line x1=\"0\" y1=\"{y:.3}\" x2=\"{}\" y2=\"{y:.3}\" stroke-width=\"{}\"/>",
+            spec.width,
+            spec.stroke_width(i)
+        )?;
+    }
+
+    // Diagonals start along the top edge, and enough of them to cover the whole box
+    let run = spec.height / 3f64.sqrt();
+    let extra = (run / spec.cell).ceil() as i64;
+    let count = (spec.width / spec.cell).ceil() as i64;
+    for k in -extra..=count + extra {
+        let x = k as f64 * spec.cell;
+        let width = spec.stroke_width(k.unsigned_abs() as usize);
+        for direction in [1.0, -1.0] {
+            writeln!(
+                svg,
+                "<line x1=\"{x:.3}\" y1=\"0\" x2=\"{:.3}\" y2=\"{}\" stroke-width=\"{width}\"/>",
+                x + direction * run,
+                spec.height
+            )?;
+        }
+    }
+    Ok(())
+}
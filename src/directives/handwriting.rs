@@ -0,0 +1,100 @@
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+use crate::html_gen::{escape_attribute, escape_html};
+
+// Row width in millimetres, roughly the printable width of a Letter or A4 page
+const ROW_WIDTH: f64 = 180.0;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+struct HandwritingSpec {
+    rows: usize,
+    /// Distance from baseline to top line, in millimetres
+    line_height: f64,
+    midline: bool,
+    descender: bool,
+    trace: Option<String>,
+    /// Rows that get trace text, counting from the top (defaults to all of them)
+    trace_rows: Option<usize>,
+    font: String,
+}
+
+impl Default for HandwritingSpec {
+    fn default() -> Self {
+        HandwritingSpec {
+            rows: 4,
+            line_height: 15.0,
+            midline: true,
+            descender: true,
+            trace: None,
+            trace_rows: None,
+            font: "sans-serif".to_string(),
+        }
+    }
+}
+
+pub fn render(body: &str) -> Result<String> {
+    let spec: HandwritingSpec = if body.trim().is_empty() {
+        HandwritingSpec::default()
+    } else {
+        serde_yaml::from_str(body)?
+    };
+    if spec.line_height <= 0.0 {
+        return Err(anyhow!("line_height must be positive"));
+    }
+
+    let top = spec.line_height * 0.1;
+    let baseline = top + spec.line_height;
+    let row_height = baseline + spec.line_height * if spec.descender { 0.5 } else { 0.2 };
+    let trace_rows = spec.trace_rows.unwrap_or(spec.rows);
+
+    let mut html = String::from("<div class=\"handwriting\">\n");
+    for row in 0..spec.rows {
+        writeln!(
+            html,
+            "<svg class=\"handwriting-row\" xmlns=\"http://www.w3.org/2000/svg\" \
+             viewBox=\"0 0 {ROW_WIDTH} {row_height:.2}\" width=\"{ROW_WIDTH}mm\" \
+             height=\"{row_height:.2}mm\">"
+        )?;
+
+        if let Some(trace) = spec.trace.as_ref().filter(|_| row < trace_rows) {
+            // Size the text so capitals reach the top line
+            writeln!(
+                html,
+                "<text x=\"2\" y=\"{baseline:.2}\" font-size=\"{:.2}\" font-family=\"{}\" \
+                 fill=\"#bbb\">{}</text>",
+                spec.line_height * 1.4,
+                escape_attribute(&spec.font),
+                escape_html(trace)
+            )?;
+        }
+
+        let mut line = |y: f64, style: &str| {
+            writeln!(
+                html,
+                "<line x1=\"0\" y1=\"{y:.2}\" x2=\"{ROW_WIDTH}\" y2=\"{y:.2}\" {style}/>"
+            )
+        };
+        line(top, "stroke=\"#000\" stroke-width=\"0.3\"")?;
+        if spec.midline {
+            line(
+                top + spec.line_height / 2.0,
+                "stroke=\"#666\" stroke-width=\"0.25\" stroke-dasharray=\"2 1.5\"",
+            )?;
+        }
+        line(baseline, "stroke=\"#000\" stroke-width=\"0.4\"")?;
+        if spec.descender {
+            line(
+                baseline + spec.line_height / 2.0,
+                "stroke=\"#999\" stroke-width=\"0.2\" stroke-dasharray=\"0.5 1\"",
+            )?;
+        }
+        html.push_str("</svg>\n");
+    }
+    html.push_str("</div>\n");
+
+    Ok(html)
+}
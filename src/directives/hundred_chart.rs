@@ -0,0 +1,91 @@
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use rand::seq::index;
+use serde::Deserialize;
+
+use crate::context::RenderContext;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+struct HundredChartSpec {
+    start: i64,
+    end: i64,
+    columns: usize,
+    /// How many numbers to leave out for the student to fill in
+    missing: usize,
+    /// Highlight multiples of this number, to show skip-counting patterns
+    highlight: Option<i64>,
+}
+
+impl Default for HundredChartSpec {
+    fn default() -> Self {
+        HundredChartSpec {
+            start: 1,
+            end: 100,
+            columns: 10,
+            missing: 0,
+            highlight: None,
+        }
+    }
+}
+
+pub fn render(body: &str, ctx: &mut RenderContext) -> Result<String> {
+    let spec: HundredChartSpec = if body.trim().is_empty() {
+        HundredChartSpec::default()
+    } else {
+        serde_yaml::from_str(body)?
+    };
+    if spec.start > spec.end {
+        return Err(anyhow!("start must not be after end"));
+    }
+    if spec.columns == 0 {
+        return Err(anyhow!("columns must be at least 1"));
+    }
+    if spec.highlight == Some(0) {
+        return Err(anyhow!("highlight must not be 0"));
+    }
+
+    let numbers: Vec<i64> = (spec.start..=spec.end).collect();
+    if spec.missing > numbers.len() {
+        return Err(anyhow!(
+            "Can't leave out {} of only {} numbers",
+            spec.missing,
+            numbers.len()
+        ));
+    }
+    let mut is_missing = vec![false; numbers.len()];
+    for i in index::sample(&mut ctx.rng, numbers.len(), spec.missing) {
+        is_missing[i] = true;
+    }
+
+    let mut html = String::from("<table class=\"hundred-chart\">\n");
+    for (row_index, row) in numbers.chunks(spec.columns).enumerate() {
+        html.push_str("<tr>");
+        for (column_index, &number) in row.iter().enumerate() {
+            let mut classes = vec![];
+            if spec.highlight.is_some_and(|n| number % n == 0) {
+                classes.push("hundred-chart-highlight");
+            }
+            let content = if !is_missing[row_index * spec.columns + column_index] {
+                number.to_string()
+            } else {
+                classes.push("hundred-chart-blank");
+                if ctx.show_answers {
+                    format!("<span class=\"answer\">{number}</span>")
+                } else {
+                    String::new()
+                }
+            };
+            if classes.is_empty() {
+                write!(html, "<td>{content}</td>")?;
+            } else {
+                write!(html, "<td class=\"{}\">{content}</td>", classes.join(" "))?;
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+
+    Ok(html)
+}
@@ -0,0 +1,68 @@
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use rand::seq::SliceRandom;
+
+use crate::context::RenderContext;
+use crate::html_gen::escape_html;
+use crate::inline::render_inline_answer;
+
+const LETTERS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+struct Pair {
+    term: String,
+    definition: String,
+}
+
+/// Render `term: definition` lines as two shuffled columns, with a blank by each term for the
+/// letter of its definition.
+pub fn render(body: &str, ctx: &mut RenderContext) -> Result<String> {
+    let pairs = parse_pairs(body)?;
+    if pairs.len() < 2 || pairs.len() > LETTERS.len() {
+        return Err(anyhow!("Expected between 2 and 26 pairs"));
+    }
+
+    let mut term_order: Vec<usize> = (0..pairs.len()).collect();
+    let mut definition_order = term_order.clone();
+    term_order.shuffle(&mut ctx.rng);
+    definition_order.shuffle(&mut ctx.rng);
+
+    let letters: Vec<char> = LETTERS.chars().collect();
+    let mut html = String::from("<table class=\"matching\">\n");
+    for (row, (&term_index, &definition_index)) in
+        term_order.iter().zip(&definition_order).enumerate()
+    {
+        let answer_position = definition_order
+            .iter()
+            .position(|&i| i == term_index)
+            .unwrap();
+        writeln!(
+            html,
+            "<tr><td class=\"matching-blank\">{}</td><td class=\"matching-term\">{}. {}</td>\
+             <td class=\"matching-definition\">{}. {}</td></tr>",
+            render_inline_answer(&letters[answer_position].to_string(), ctx.show_answers),
+            row + 1,
+            escape_html(&pairs[term_index].term),
+            letters[row],
+            escape_html(&pairs[definition_index].definition),
+        )?;
+    }
+    html.push_str("</table>\n");
+
+    Ok(html)
+}
+
+fn parse_pairs(body: &str) -> Result<Vec<Pair>> {
+    body.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (term, definition) = line
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Expected `term: definition`, got {line:?}"))?;
+            Ok(Pair {
+                term: term.trim().to_string(),
+                definition: definition.trim().to_string(),
+            })
+        })
+        .collect()
+}
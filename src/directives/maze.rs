@@ -0,0 +1,219 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use rand::Rng;
+use rand::seq::IndexedRandom;
+use serde::Deserialize;
+
+use crate::context::RenderContext;
+
+// Size of one maze cell in SVG user units
+const CELL_SIZE: usize = 20;
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct MazeSpec {
+    width: Option<usize>,
+    height: Option<usize>,
+    #[serde(default)]
+    difficulty: Difficulty,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    fn default_size(self) -> usize {
+        match self {
+            Difficulty::Easy => 8,
+            Difficulty::Medium => 15,
+            Difficulty::Hard => 25,
+        }
+    }
+}
+
+/// A grid of cells where each cell records whether it opens to the right and downward.
+struct Maze {
+    width: usize,
+    height: usize,
+    open_right: Vec<bool>,
+    open_down: Vec<bool>,
+}
+
+impl Maze {
+    /// Carve a perfect maze with a randomized depth-first search.
+    fn generate(width: usize, height: usize, rng: &mut impl Rng) -> Self {
+        let mut maze = Maze {
+            width,
+            height,
+            open_right: vec![false; width * height],
+            open_down: vec![false; width * height],
+        };
+        let mut visited = vec![false; width * height];
+        let mut stack = vec![0];
+        visited[0] = true;
+
+        while let Some(&cell) = stack.last() {
+            let unvisited: Vec<usize> = maze
+                .neighbors(cell)
+                .into_iter()
+                .filter(|&neighbor| !visited[neighbor])
+                .collect();
+            match unvisited.choose(rng) {
+                Some(&next) => {
+                    maze.open_between(cell, next);
+                    visited[next] = true;
+                    stack.push(next);
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+
+        maze
+    }
+
+    fn neighbors(&self, cell: usize) -> Vec<usize> {
+        let (x, y) = (cell % self.width, cell / self.width);
+        let mut neighbors = vec![];
+        if x > 0 {
+            neighbors.push(cell - 1);
+        }
+        if x + 1 < self.width {
+            neighbors.push(cell + 1);
+        }
+        if y > 0 {
+            neighbors.push(cell - self.width);
+        }
+        if y + 1 < self.height {
+            neighbors.push(cell + self.width);
+        }
+        neighbors
+    }
+
+    fn open_between(&mut self, a: usize, b: usize) {
+        let (low, high) = (a.min(b), a.max(b));
+        if high == low + 1 {
+            self.open_right[low] = true;
+        } else {
+            self.open_down[low] = true;
+        }
+    }
+
+    fn is_open(&self, a: usize, b: usize) -> bool {
+        let (low, high) = (a.min(b), a.max(b));
+        if high == low + 1 && low % self.width + 1 < self.width {
+            self.open_right[low]
+        } else if high == low + self.width {
+            self.open_down[low]
+        } else {
+            false
+        }
+    }
+
+    /// Cells from the top-left entrance to the bottom-right exit.
+    fn solve(&self) -> Vec<usize> {
+        let goal = self.width * self.height - 1;
+        let mut previous = vec![None; self.width * self.height];
+        let mut queue = VecDeque::from([0]);
+        previous[0] = Some(0);
+
+        while let Some(cell) = queue.pop_front() {
+            if cell == goal {
+                break;
+            }
+            for neighbor in self.neighbors(cell) {
+                if previous[neighbor].is_none() && self.is_open(cell, neighbor) {
+                    previous[neighbor] = Some(cell);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        let mut path = vec![goal];
+        let mut cell = goal;
+        while cell != 0 {
+            cell = previous[cell].expect("Perfect mazes are fully connected");
+            path.push(cell);
+        }
+        path.reverse();
+        path
+    }
+}
+
+pub fn render(body: &str, ctx: &mut RenderContext) -> Result<String> {
+    let spec: MazeSpec = if body.trim().is_empty() {
+        MazeSpec::default()
+    } else {
+        serde_yaml::from_str(body)?
+    };
+    let width = spec.width.unwrap_or(spec.difficulty.default_size());
+    let height = spec.height.unwrap_or(width);
+    if width < 2 || height < 2 {
+        return Err(anyhow!("Mazes must be at least 2×2"));
+    }
+
+    let maze = Maze::generate(width, height, &mut ctx.rng);
+    render_svg(&maze, ctx.show_answers)
+}
+
+fn render_svg(maze: &Maze, show_answers: bool) -> Result<String> {
+    let (width, height) = (maze.width * CELL_SIZE, maze.height * CELL_SIZE);
+    let mut svg = format!(
+        "<svg class=\"maze\" xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-2 -2 {} {}\">\n\
+         <g stroke=\"black\" stroke-width=\"2\" stroke-linecap=\"square\">\n",
+        width + 4,
+        height + 4
+    );
+
+    // Outer walls, leaving gaps for the entrance (top-left) and exit (bottom-right)
+    let mut line = |x1: usize, y1: usize, x2: usize, y2: usize| {
+        writeln!(
+            svg,
+            "<line x1=\"{x1}\" y1=\"{y1}\" x2=\"{x2}\" y2=\"{y2}\"/>"
+        )
+    };
+    line(CELL_SIZE, 0, width, 0)?;
+    line(0, 0, 0, height)?;
+    for cell in 0..maze.width * maze.height {
+        let (x, y) = (cell % maze.width * CELL_SIZE, cell / maze.width * CELL_SIZE);
+        let is_last_column = cell % maze.width + 1 == maze.width;
+        let is_last_row = cell / maze.width + 1 == maze.height;
+        let is_exit = cell + 1 == maze.width * maze.height;
+        if is_last_column || !maze.open_right[cell] {
+            line(x + CELL_SIZE, y, x + CELL_SIZE, y + CELL_SIZE)?;
+        }
+        if (is_last_row && !is_exit) || (!is_last_row && !maze.open_down[cell]) {
+            line(x, y + CELL_SIZE, x + CELL_SIZE, y + CELL_SIZE)?;
+        }
+    }
+    svg.push_str("</g>\n");
+
+    if show_answers {
+        let center = |cell: usize| {
+            let x = cell % maze.width * CELL_SIZE + CELL_SIZE / 2;
+            let y = cell / maze.width * CELL_SIZE + CELL_SIZE / 2;
+            format!("{x},{y}")
+        };
+        let mut points: Vec<String> = vec![format!("{},0", CELL_SIZE / 2)];
+        points.extend(maze.solve().into_iter().map(center));
+        points.push(format!("{},{height}", width - CELL_SIZE / 2));
+        writeln!(
+            svg,
+            "<polyline class=\"maze-solution\" points=\"{}\" fill=\"none\" stroke=\"#c0392b\" \
+             stroke-width=\"4\" stroke-linejoin=\"round\"/>",
+            points.join(" ")
+        )?;
+    }
+    svg.push_str("</svg>\n");
+
+    Ok(svg)
+}
@@ -0,0 +1,26 @@
+use std::process::Command;
+
+use anyhow::Result;
+
+use super::{pipe_through, strip_xml_prolog};
+use crate::pdf_gen::check_executable;
+
+/// Render a Mermaid diagram to inline SVG with the Mermaid CLI (`mmdc`).
+pub fn render(body: &str) -> Result<String> {
+    check_executable("mmdc")?;
+    let mut command = Command::new("mmdc");
+    command.args([
+        "--input",
+        "-",
+        "--output",
+        "-",
+        "--outputFormat",
+        "svg",
+        "--quiet",
+    ]);
+    let svg = pipe_through(command, "mmdc", body)?;
+    Ok(format!(
+        "<div class=\"mermaid-diagram\">\n{}\n</div>\n",
+        strip_xml_prolog(&svg)
+    ))
+}
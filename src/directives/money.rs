@@ -0,0 +1,252 @@
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use rand::Rng;
+use rand::seq::IndexedRandom;
+use serde::Deserialize;
+
+use crate::context::RenderContext;
+use crate::inline::render_inline_answer;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MoneySpec {
+    #[serde(default)]
+    currency: Currency,
+    /// Names of the coins and bills to draw from (defaults to all the currency's coins)
+    #[serde(default)]
+    denominations: Vec<String>,
+    /// Range for the number of coins and bills in each problem
+    #[serde(default = "default_items")]
+    items: [usize; 2],
+    #[serde(default = "default_count")]
+    count: usize,
+    #[serde(default = "default_columns")]
+    columns: usize,
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Currency {
+    #[default]
+    Usd,
+    Eur,
+}
+
+struct Denomination {
+    name: &'static str,
+    cents: u32,
+    is_coin: bool,
+    svg: &'static str,
+}
+
+const USD: &[Denomination] = &[
+    Denomination {
+        name: "penny",
+        cents: 1,
+        is_coin: true,
+        svg: include_str!("../../assets/money/usd/penny.svg"),
+    },
+    Denomination {
+        name: "nickel",
+        cents: 5,
+        is_coin: true,
+        svg: include_str!("../../assets/money/usd/nickel.svg"),
+    },
+    Denomination {
+        name: "dime",
+        cents: 10,
+        is_coin: true,
+        svg: include_str!("../../assets/money/usd/dime.svg"),
+    },
+    Denomination {
+        name: "quarter",
+        cents: 25,
+        is_coin: true,
+        svg: include_str!("../../assets/money/usd/quarter.svg"),
+    },
+    Denomination {
+        name: "one",
+        cents: 100,
+        is_coin: false,
+        svg: include_str!("../../assets/money/usd/one.svg"),
+    },
+    Denomination {
+        name: "five",
+        cents: 500,
+        is_coin: false,
+        svg: include_str!("../../assets/money/usd/five.svg"),
+    },
+    Denomination {
+        name: "ten",
+        cents: 1000,
+        is_coin: false,
+        svg: include_str!("../../assets/money/usd/ten.svg"),
+    },
+    Denomination {
+        name: "twenty",
+        cents: 2000,
+        is_coin: false,
+        svg: include_str!("../../assets/money/usd/twenty.svg"),
+    },
+];
+
+const EUR: &[Denomination] = &[
+    Denomination {
+        name: "1c",
+        cents: 1,
+        is_coin: true,
+        svg: include_str!("../../assets/money/eur/1c.svg"),
+    },
+    Denomination {
+        name: "2c",
+        cents: 2,
+        is_coin: true,
+        svg: include_str!("../../assets/money/eur/2c.svg"),
+    },
+    Denomination {
+        name: "5c",
+        cents: 5,
+        is_coin: true,
+        svg: include_str!("../../assets/money/eur/5c.svg"),
+    },
+    Denomination {
+        name: "10c",
+        cents: 10,
+        is_coin: true,
+        svg: include_str!("../../assets/money/eur/10c.svg"),
+    },
+    Denomination {
+        name: "20c",
+        cents: 20,
+        is_coin: true,
+        svg: include_str!("../../assets/money/eur/20c.svg"),
+    },
+    Denomination {
+        name: "50c",
+        cents: 50,
+        is_coin: true,
+        svg: include_str!("../../assets/money/eur/50c.svg"),
+    },
+    Denomination {
+        name: "1e",
+        cents: 100,
+        is_coin: true,
+        svg: include_str!("../../assets/money/eur/1e.svg"),
+    },
+    Denomination {
+        name: "2e",
+        cents: 200,
+        is_coin: true,
+        svg: include_str!("../../assets/money/eur/2e.svg"),
+    },
+    Denomination {
+        name: "5e",
+        cents: 500,
+        is_coin: false,
+        svg: include_str!("../../assets/money/eur/5e.svg"),
+    },
+    Denomination {
+        name: "10e",
+        cents: 1000,
+        is_coin: false,
+        svg: include_str!("../../assets/money/eur/10e.svg"),
+    },
+    Denomination {
+        name: "20e",
+        cents: 2000,
+        is_coin: false,
+        svg: include_str!("../../assets/money/eur/20e.svg"),
+    },
+];
+
+impl Currency {
+    fn denominations(self) -> &'static [Denomination] {
+        match self {
+            Currency::Usd => USD,
+            Currency::Eur => EUR,
+        }
+    }
+
+    fn format(self, cents: u32) -> String {
+        match self {
+            Currency::Usd => format!("${}.{:02}", cents / 100, cents % 100),
+            Currency::Eur => format!("€{},{:02}", cents / 100, cents % 100),
+        }
+    }
+}
+
+fn default_items() -> [usize; 2] {
+    [3, 6]
+}
+
+fn default_count() -> usize {
+    6
+}
+
+fn default_columns() -> usize {
+    2
+}
+
+pub fn render(body: &str, ctx: &mut RenderContext) -> Result<String> {
+    let spec: MoneySpec = if body.trim().is_empty() {
+        serde_yaml::from_str("{}")?
+    } else {
+        serde_yaml::from_str(body)?
+    };
+    let [min_items, max_items] = spec.items;
+    if min_items == 0 || min_items > max_items {
+        return Err(anyhow!("Invalid item range [{min_items}, {max_items}]"));
+    }
+    if spec.columns == 0 {
+        return Err(anyhow!("columns must be at least 1"));
+    }
+
+    let available = spec.currency.denominations();
+    let denominations: Vec<&Denomination> = if spec.denominations.is_empty() {
+        available.iter().filter(|d| d.is_coin).collect()
+    } else {
+        spec.denominations
+            .iter()
+            .map(|name| {
+                available.iter().find(|d| d.name == name).ok_or_else(|| {
+                    let names: Vec<_> = available.iter().map(|d| d.name).collect();
+                    anyhow!("Unknown denomination {name:?} (expected one of {names:?})")
+                })
+            })
+            .collect::<Result<_>>()?
+    };
+
+    let problems: Vec<Vec<&Denomination>> = (0..spec.count)
+        .map(|_| {
+            let item_count = ctx.rng.random_range(min_items..=max_items);
+            let mut items: Vec<&Denomination> = (0..item_count)
+                .map(|_| *denominations.choose(&mut ctx.rng).unwrap())
+                .collect();
+            // Largest first, the way you'd count it out
+            items.sort_by_key(|d| std::cmp::Reverse(d.cents));
+            items
+        })
+        .collect();
+
+    let mut html = String::from("<table class=\"money\">\n");
+    for row in problems.chunks(spec.columns) {
+        html.push_str("<tr>");
+        for items in row {
+            let total = items.iter().map(|d| d.cents).sum();
+            html.push_str("<td class=\"money-problem\"><div class=\"money-items\">");
+            for item in items {
+                html.push_str(item.svg.trim());
+            }
+            write!(
+                html,
+                "</div><div class=\"money-total\">Total: {}</div></td>",
+                render_inline_answer(&spec.currency.format(total), ctx.show_answers)
+            )?;
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+
+    Ok(html)
+}
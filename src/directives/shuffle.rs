@@ -0,0 +1,57 @@
+use anyhow::Result;
+use rand::seq::SliceRandom;
+
+use crate::context::RenderContext;
+use crate::html_gen::render_markdown;
+
+/// Render a markdown list with its top-level items in random order.
+///
+/// Ordered lists are renumbered after shuffling. Anything before the first item is kept in
+/// place as an introduction.
+pub fn render(body: &str, ctx: &mut RenderContext) -> Result<String> {
+    let mut intro = String::new();
+    let mut items: Vec<String> = vec![];
+    for line in body.lines() {
+        if list_marker_length(line).is_some() {
+            items.push(String::new());
+        }
+        let target = items.last_mut().unwrap_or(&mut intro);
+        target.push_str(line);
+        target.push('\n');
+    }
+
+    items.shuffle(&mut ctx.rng);
+
+    let mut markdown = intro;
+    for (i, item) in items.iter().enumerate() {
+        let marker_length = list_marker_length(item).unwrap_or(0);
+        let is_ordered = item.starts_with(|c: char| c.is_ascii_digit());
+        if is_ordered {
+            markdown.push_str(&format!("{}. ", i + 1));
+        } else {
+            markdown.push_str("- ");
+        }
+        markdown.push_str(&item[marker_length..]);
+    }
+
+    render_markdown(&markdown, ctx)
+}
+
+/// The length of a top-level list marker (including the following space), if the line has one.
+fn list_marker_length(line: &str) -> Option<usize> {
+    if let Some(rest) = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))
+    {
+        return Some(line.len() - rest.len());
+    }
+
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    let rest = &line[digits..];
+    if digits > 0 && (rest.starts_with(". ") || rest.starts_with(") ")) {
+        Some(digits + 2)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,254 @@
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+
+use crate::context::RenderContext;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SudokuSpec {
+    #[serde(default)]
+    difficulty: Difficulty,
+    #[serde(default = "default_size")]
+    size: usize,
+}
+
+impl Default for SudokuSpec {
+    fn default() -> Self {
+        SudokuSpec {
+            difficulty: Difficulty::default(),
+            size: default_size(),
+        }
+    }
+}
+
+fn default_size() -> usize {
+    9
+}
+
+#[derive(Debug, Deserialize, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    /// Fraction of cells left filled in.
+    fn given_ratio(self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.5,
+            Difficulty::Medium => 0.4,
+            Difficulty::Hard => 0.32,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Grid {
+    box_size: usize,
+    size: usize,
+    // Zero means empty
+    cells: Vec<u8>,
+}
+
+impl Grid {
+    fn new(box_size: usize) -> Self {
+        let size = box_size * box_size;
+        Grid {
+            box_size,
+            size,
+            cells: vec![0; size * size],
+        }
+    }
+
+    fn candidates(&self, index: usize) -> Vec<u8> {
+        let (row, column) = (index / self.size, index % self.size);
+        let (box_row, box_column) = (
+            row / self.box_size * self.box_size,
+            column / self.box_size * self.box_size,
+        );
+        let mut used = vec![false; self.size + 1];
+        for i in 0..self.size {
+            used[self.cells[row * self.size + i] as usize] = true;
+            used[self.cells[i * self.size + column] as usize] = true;
+            let (r, c) = (box_row + i / self.box_size, box_column + i % self.box_size);
+            used[self.cells[r * self.size + c] as usize] = true;
+        }
+        (1..=self.size as u8)
+            .filter(|&digit| !used[digit as usize])
+            .collect()
+    }
+
+    /// The empty cell with the fewest candidates, and those candidates.
+    fn most_constrained(&self) -> Option<(usize, Vec<u8>)> {
+        (0..self.cells.len())
+            .filter(|&index| self.cells[index] == 0)
+            .map(|index| (index, self.candidates(index)))
+            .min_by_key(|(_, candidates)| candidates.len())
+    }
+
+    /// Fill every empty cell, trying digits in random order.
+    fn fill(&mut self, rng: &mut impl Rng) -> bool {
+        let Some((index, mut candidates)) = self.most_constrained() else {
+            return true;
+        };
+        candidates.shuffle(rng);
+        for digit in candidates {
+            self.cells[index] = digit;
+            if self.fill(rng) {
+                return true;
+            }
+        }
+        self.cells[index] = 0;
+        false
+    }
+
+    /// Count solutions, stopping once `limit` is reached.
+    fn count_solutions(&mut self, limit: usize) -> usize {
+        let Some((index, candidates)) = self.most_constrained() else {
+            return 1;
+        };
+        let mut count = 0;
+        for digit in candidates {
+            self.cells[index] = digit;
+            count += self.count_solutions(limit - count);
+            if count >= limit {
+                break;
+            }
+        }
+        self.cells[index] = 0;
+        count
+    }
+}
+
+pub fn render(body: &str, ctx: &mut RenderContext) -> Result<String> {
+    let spec: SudokuSpec = if body.trim().is_empty() {
+        SudokuSpec::default()
+    } else {
+        serde_yaml::from_str(body)?
+    };
+    let (puzzle, solution) = generate(&spec, &mut ctx.rng)?;
+
+    render_grid(&puzzle, &solution, ctx.show_answers)
+}
+
+/// A puzzle with a unique solution, and that solution.
+fn generate(spec: &SudokuSpec, rng: &mut impl Rng) -> Result<(Grid, Grid)> {
+    let box_size = match spec.size {
+        4 => 2,
+        9 => 3,
+        size => return Err(anyhow!("Unsupported size {size} (expected 4 or 9)")),
+    };
+
+    let mut solution = Grid::new(box_size);
+    solution.fill(rng);
+
+    // Remove clues one at a time, keeping only removals that leave a unique solution
+    let mut puzzle = solution.clone();
+    let target_givens = (puzzle.cells.len() as f64 * spec.difficulty.given_ratio()).ceil() as usize;
+    let mut order: Vec<usize> = (0..puzzle.cells.len()).collect();
+    order.shuffle(rng);
+    let mut givens = puzzle.cells.len();
+    for index in order {
+        if givens <= target_givens {
+            break;
+        }
+        let digit = puzzle.cells[index];
+        puzzle.cells[index] = 0;
+        if puzzle.clone().count_solutions(2) == 1 {
+            givens -= 1;
+        } else {
+            puzzle.cells[index] = digit;
+        }
+    }
+
+    Ok((puzzle, solution))
+}
+
+fn render_grid(puzzle: &Grid, solution: &Grid, show_answers: bool) -> Result<String> {
+    let mut html = format!("<table class=\"sudoku sudoku-{}\">\n", puzzle.size);
+    for row in 0..puzzle.size {
+        let row_class = if row % puzzle.box_size == 0 && row > 0 {
+            " class=\"sudoku-box-top\""
+        } else {
+            ""
+        };
+        write!(html, "<tr{row_class}>")?;
+        for column in 0..puzzle.size {
+            let index = row * puzzle.size + column;
+            let cell_class = if column % puzzle.box_size == 0 && column > 0 {
+                " sudoku-box-left"
+            } else {
+                ""
+            };
+            let content = match puzzle.cells[index] {
+                0 if show_answers => {
+                    format!("<span class=\"answer\">{}</span>", solution.cells[index])
+                }
+                0 => String::new(),
+                digit => digit.to_string(),
+            };
+            let kind = if puzzle.cells[index] == 0 {
+                "sudoku-blank"
+            } else {
+                "sudoku-given"
+            };
+            write!(html, "<td class=\"{kind}{cell_class}\">{content}</td>")?;
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use super::*;
+
+    // Every size and difficulty, from a few seeds
+    fn puzzles() -> Vec<(SudokuSpec, Grid, Grid)> {
+        let mut puzzles = vec![];
+        for size in [4, 9] {
+            for difficulty in [Difficulty::Easy, Difficulty::Medium, Difficulty::Hard] {
+                for seed in 0..5 {
+                    let spec = SudokuSpec { difficulty, size };
+                    let (puzzle, solution) =
+                        generate(&spec, &mut StdRng::seed_from_u64(seed)).unwrap();
+                    puzzles.push((spec, puzzle, solution));
+                }
+            }
+        }
+        puzzles
+    }
+
+    #[test]
+    fn puzzles_have_exactly_one_solution() {
+        for (_, puzzle, solution) in puzzles() {
+            assert_eq!(puzzle.clone().count_solutions(2), 1);
+            for (given, digit) in puzzle.cells.iter().zip(&solution.cells) {
+                assert!(*given == 0 || given == digit);
+            }
+            assert!(solution.cells.iter().all(|&digit| digit != 0));
+        }
+    }
+
+    #[test]
+    fn puzzles_have_the_givens_the_difficulty_asks_for() {
+        for (spec, puzzle, _) in puzzles() {
+            let cells = spec.size * spec.size;
+            let expected = (cells as f64 * spec.difficulty.given_ratio()).ceil() as usize;
+            let givens = puzzle.cells.iter().filter(|&&digit| digit != 0).count();
+            assert_eq!(givens, expected, "{spec:?}");
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::fmt::Write;
+
+use anyhow::{Result, anyhow};
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::context::RenderContext;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+struct TimesTableSpec {
+    /// Factors down the left side, as `[min, max]`
+    rows: [u32; 2],
+    /// Factors across the top, as `[min, max]`
+    columns: [u32; 2],
+    /// Fraction of products left blank for the student to fill in
+    blanks: f64,
+}
+
+impl Default for TimesTableSpec {
+    fn default() -> Self {
+        TimesTableSpec {
+            rows: [1, 10],
+            columns: [1, 10],
+            blanks: 0.3,
+        }
+    }
+}
+
+pub fn render(body: &str, ctx: &mut RenderContext) -> Result<String> {
+    let spec: TimesTableSpec = if body.trim().is_empty() {
+        TimesTableSpec::default()
+    } else {
+        serde_yaml::from_str(body)?
+    };
+    for [min, max] in [spec.rows, spec.columns] {
+        if min > max {
+            return Err(anyhow!("Invalid factor range [{min}, {max}]"));
+        }
+    }
+    if !(0.0..=1.0).contains(&spec.blanks) {
+        return Err(anyhow!("blanks must be between 0 and 1"));
+    }
+
+    let [column_min, column_max] = spec.columns;
+    let mut html = String::from("<table class=\"times-table\">\n<tr><th>×</th>");
+    for column in column_min..=column_max {
+        write!(html, "<th>{column}</th>")?;
+    }
+    html.push_str("</tr>\n");
+
+    for row in spec.rows[0]..=spec.rows[1] {
+        write!(html, "<tr><th>{row}</th>")?;
+        for column in column_min..=column_max {
+            let product = row * column;
+            let cell = if !ctx.rng.random_bool(spec.blanks) {
+                format!("<td>{product}</td>")
+            } else if ctx.show_answers {
+                format!(
+                    "<td class=\"times-table-blank\"><span class=\"answer\">{product}</span></td>"
+                )
+            } else {
+                "<td class=\"times-table-blank\"></td>".to_string()
+            };
+            html.push_str(&cell);
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+
+    Ok(html)
+}
@@ -0,0 +1,24 @@
+use crate::context::RenderContext;
+use crate::html_gen::escape_html;
+
+/// A box listing the page's cloze words, plus any distractors given one per line.
+pub fn render(body: &str, ctx: &RenderContext) -> String {
+    let mut words: Vec<&str> = ctx
+        .cloze_words
+        .iter()
+        .map(String::as_str)
+        .chain(body.lines().map(str::trim).filter(|line| !line.is_empty()))
+        .collect();
+    words.sort_by_key(|word| word.to_lowercase());
+    words.dedup();
+
+    let mut html = String::from("<div class=\"word-bank\">");
+    for word in words {
+        html.push_str(&format!(
+            "<span class=\"word-bank-word\">{}</span>",
+            escape_html(word)
+        ));
+    }
+    html.push_str("</div>\n");
+    html
+}
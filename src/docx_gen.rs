@@ -0,0 +1,339 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use comrak::nodes::{AstNode, ListType, NodeValue};
+use comrak::{Arena, parse_document};
+use docx_rs::{
+    BreakType, Docx, Paragraph, Pic, Run, RunFonts, Style, StyleType, Table, TableCell, TableRow,
+};
+
+use crate::config::Options;
+use crate::context::RenderContext;
+use crate::export::{self, CodeBlock};
+use crate::page::Page;
+
+// Heading font sizes in half-points, indexed by level
+const HEADING_SIZES: [usize; 6] = [40, 32, 28, 24, 22, 22];
+
+/// Inline formatting inherited from enclosing nodes.
+#[derive(Debug, Clone, Copy, Default)]
+struct RunStyle {
+    bold: bool,
+    italic: bool,
+    strike: bool,
+    underline: bool,
+    code: bool,
+}
+
+impl RunStyle {
+    fn run(self, text: &str) -> Run {
+        let mut run = Run::new().add_text(text);
+        if self.bold {
+            run = run.bold();
+        }
+        if self.italic {
+            run = run.italic();
+        }
+        if self.strike {
+            run = run.strike();
+        }
+        if self.underline {
+            run = run.underline("single");
+        }
+        if self.code {
+            run = run.fonts(RunFonts::new().ascii("Courier New"));
+        }
+        run
+    }
+}
+
+/// Convert markdown pages into a Word document.
+///
+/// Raw HTML has no Word equivalent and is dropped. Answers and callouts are kept, but other
+/// generated exercises are an error.
+pub fn generate_docx(options: &Options, show_answers: bool, output: &Path) -> Result<()> {
+    let docx = render_docx(options, show_answers)?;
+    let file = fs::File::create(output)
+        .context(format!("Failed to create DOCX file: {}", output.display()))?;
+    docx.build()
+        .pack(file)
+        .context(format!("Failed to write DOCX to {}", output.display()))?;
+
+    Ok(())
+}
+
+/// Render the pages as a Word document, without writing it anywhere.
+pub fn render_docx(options: &Options, show_answers: bool) -> Result<Docx> {
+    let mut docx = (1..=6).fold(Docx::new(), |docx, level| {
+        docx.add_style(
+            Style::new(format!("Heading{level}"), StyleType::Paragraph)
+                .name(format!("Heading {level}"))
+                .size(HEADING_SIZES[level - 1])
+                .bold(),
+        )
+    });
+
+    let mut ctx = RenderContext::new(options, show_answers);
+    for (i, page_path) in options.pages.iter().enumerate() {
+        let page = Page::load(page_path, options, &mut ctx)?;
+
+        let arena = Arena::new();
+        let markdown_options = ctx.markdown.clone();
+        let root = parse_document(&arena, &page.markdown, &markdown_options);
+        let page_dir = page_path.parent().unwrap_or_else(|| Path::new("."));
+        for node in root.descendants() {
+            if let NodeValue::Image(link) = &node.data.borrow().value {
+                let path = page_dir.join(&link.url);
+                if path.is_file() {
+                    ctx.images.insert(path);
+                } else {
+                    ctx.warnings.warn(
+                        format!("Image {} not found", path.display()),
+                        "leaving it out",
+                    )?;
+                }
+            }
+        }
+
+        if i > 0 {
+            docx =
+                docx.add_paragraph(Paragraph::new().add_run(Run::new().add_break(BreakType::Page)));
+        }
+        docx = render_blocks(root, docx, page_dir, 0, &mut ctx)
+            .context(format!("Failed to convert {}", page_path.display()))?;
+    }
+    ctx.log_render();
+
+    Ok(docx)
+}
+
+fn render_blocks<'a>(
+    node: &'a AstNode<'a>,
+    docx: Docx,
+    page_dir: &Path,
+    depth: usize,
+    ctx: &mut RenderContext,
+) -> Result<Docx> {
+    node.children().try_fold(docx, |docx, child| {
+        render_block(child, docx, page_dir, depth, ctx)
+    })
+}
+
+fn render_block<'a>(
+    node: &'a AstNode<'a>,
+    docx: Docx,
+    page_dir: &Path,
+    depth: usize,
+    ctx: &mut RenderContext,
+) -> Result<Docx> {
+    let docx = match &node.data.borrow().value {
+        NodeValue::Paragraph => {
+            let paragraph = render_inlines(node, Paragraph::new(), RunStyle::default(), page_dir);
+            docx.add_paragraph(paragraph)
+        }
+        NodeValue::Heading(heading) => {
+            let paragraph = Paragraph::new().style(&format!("Heading{}", heading.level));
+            docx.add_paragraph(render_inlines(
+                node,
+                paragraph,
+                RunStyle::default(),
+                page_dir,
+            ))
+        }
+        NodeValue::CodeBlock(block) => {
+            match export::code_block(&block.info, &block.literal, "DOCX", ctx)? {
+                CodeBlock::Answer(Some(answer)) => {
+                    render_markdown(answer, docx, page_dir, depth, ctx)?
+                }
+                // Room to write the answer
+                CodeBlock::Answer(None) => {
+                    (0..3).fold(docx, |docx, _| docx.add_paragraph(Paragraph::new()))
+                }
+                CodeBlock::Admonition { title, body } => {
+                    let style = RunStyle {
+                        bold: true,
+                        ..RunStyle::default()
+                    };
+                    let docx = docx.add_paragraph(Paragraph::new().add_run(style.run(&title)));
+                    render_markdown(&body, docx, page_dir, depth, ctx)?
+                }
+                CodeBlock::Code(_) => {
+                    let style = RunStyle {
+                        code: true,
+                        ..RunStyle::default()
+                    };
+                    block.literal.lines().fold(docx, |docx, line| {
+                        docx.add_paragraph(Paragraph::new().add_run(style.run(line)))
+                    })
+                }
+            }
+        }
+        NodeValue::List(list) => {
+            node.children()
+                .enumerate()
+                .try_fold(docx, |docx, (i, item)| {
+                    let marker = match list.list_type {
+                        ListType::Bullet => "•".to_string(),
+                        ListType::Ordered => format!("{}.", list.start + i),
+                    };
+                    render_list_item(item, docx, page_dir, depth, &marker, ctx)
+                })?
+        }
+        NodeValue::Table(..) => {
+            let rows = node
+                .children()
+                .enumerate()
+                .map(|(row_index, row)| {
+                    let style = RunStyle {
+                        bold: row_index == 0,
+                        ..RunStyle::default()
+                    };
+                    let cells = row
+                        .children()
+                        .map(|cell| {
+                            let paragraph = render_inlines(cell, Paragraph::new(), style, page_dir);
+                            TableCell::new().add_paragraph(paragraph)
+                        })
+                        .collect();
+                    TableRow::new(cells)
+                })
+                .collect();
+            docx.add_table(Table::new(rows))
+        }
+        NodeValue::ThematicBreak => docx.add_paragraph(Paragraph::new()),
+        NodeValue::HtmlBlock(_) | NodeValue::FrontMatter(_) => docx,
+        _ => render_blocks(node, docx, page_dir, depth, ctx)?,
+    };
+    Ok(docx)
+}
+
+// Markdown nested in a block, like an answer or a callout's content
+fn render_markdown(
+    markdown: &str,
+    docx: Docx,
+    page_dir: &Path,
+    depth: usize,
+    ctx: &mut RenderContext,
+) -> Result<Docx> {
+    let arena = Arena::new();
+    let markdown_options = ctx.markdown.clone();
+    let root = parse_document(&arena, markdown, &markdown_options);
+    render_blocks(root, docx, page_dir, depth, ctx)
+}
+
+fn render_list_item<'a>(
+    item: &'a AstNode<'a>,
+    docx: Docx,
+    page_dir: &Path,
+    depth: usize,
+    marker: &str,
+    ctx: &mut RenderContext,
+) -> Result<Docx> {
+    let indent = "    ".repeat(depth);
+    item.children()
+        .enumerate()
+        .try_fold(docx, |docx, (i, child)| {
+            let is_paragraph = matches!(child.data.borrow().value, NodeValue::Paragraph);
+            if is_paragraph {
+                let prefix = if i == 0 {
+                    format!("{indent}{marker} ")
+                } else {
+                    format!("{indent}    ")
+                };
+                let paragraph = Paragraph::new().add_run(Run::new().add_text(&prefix));
+                let paragraph = render_inlines(child, paragraph, RunStyle::default(), page_dir);
+                Ok(docx.add_paragraph(paragraph))
+            } else {
+                render_block(child, docx, page_dir, depth + 1, ctx)
+            }
+        })
+}
+
+fn render_inlines<'a>(
+    node: &'a AstNode<'a>,
+    paragraph: Paragraph,
+    style: RunStyle,
+    page_dir: &Path,
+) -> Paragraph {
+    node.children().fold(paragraph, |paragraph, child| {
+        render_inline(child, paragraph, style, page_dir)
+    })
+}
+
+fn render_inline<'a>(
+    node: &'a AstNode<'a>,
+    paragraph: Paragraph,
+    style: RunStyle,
+    page_dir: &Path,
+) -> Paragraph {
+    match &node.data.borrow().value {
+        NodeValue::Text(text) => paragraph.add_run(style.run(text)),
+        NodeValue::Code(code) => paragraph.add_run(
+            RunStyle {
+                code: true,
+                ..style
+            }
+            .run(&code.literal),
+        ),
+        NodeValue::Math(math) => paragraph.add_run(
+            RunStyle {
+                code: true,
+                ..style
+            }
+            .run(&math.literal),
+        ),
+        NodeValue::SoftBreak => paragraph.add_run(style.run(" ")),
+        NodeValue::LineBreak => paragraph.add_run(Run::new().add_break(BreakType::TextWrapping)),
+        NodeValue::Emph => render_inlines(
+            node,
+            paragraph,
+            RunStyle {
+                italic: true,
+                ..style
+            },
+            page_dir,
+        ),
+        NodeValue::Strong => render_inlines(
+            node,
+            paragraph,
+            RunStyle {
+                bold: true,
+                ..style
+            },
+            page_dir,
+        ),
+        NodeValue::Strikethrough => render_inlines(
+            node,
+            paragraph,
+            RunStyle {
+                strike: true,
+                ..style
+            },
+            page_dir,
+        ),
+        NodeValue::Link(_) => render_inlines(
+            node,
+            paragraph,
+            RunStyle {
+                underline: true,
+                ..style
+            },
+            page_dir,
+        ),
+        NodeValue::Image(link) => {
+            let path = page_dir.join(&link.url);
+            match fs::read(&path) {
+                Ok(bytes) => paragraph.add_run(Run::new().add_image(Pic::new(&bytes))),
+                // Missing images were already reported
+                Err(_) => render_inlines(node, paragraph, style, page_dir),
+            }
+        }
+        // The answer itself is text between the span tags, but the blank needs drawing
+        NodeValue::HtmlInline(html) if export::is_answer_blank(html) => {
+            paragraph.add_run(style.run("__________"))
+        }
+        NodeValue::HtmlInline(_) => paragraph,
+        _ => render_inlines(node, paragraph, style, page_dir),
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::{Result, anyhow};
+
+use crate::context::RenderContext;
+use crate::directives;
+
+/// What a fenced code block turns into in a format without HTML, like Typst or DOCX.
+pub enum CodeBlock<'a> {
+    /// An answer's markdown, or `None` when the answer is hidden and only a blank is left
+    Answer(Option<&'a str>),
+    /// A callout with its title and markdown content
+    Admonition { title: String, body: String },
+    /// Ordinary code, with its language if one was given
+    Code(Option<&'a str>),
+}
+
+/// Work out how to export a fenced code block.
+///
+/// Generated exercises can only be rendered as HTML, so directives other than callouts are an
+/// error rather than being shown as their source.
+pub fn code_block<'a>(
+    info: &'a str,
+    literal: &'a str,
+    format: &str,
+    ctx: &mut RenderContext,
+) -> Result<CodeBlock<'a>> {
+    let mut words = info.split_whitespace();
+    let Some(name) = words.next() else {
+        return Ok(CodeBlock::Code(None));
+    };
+    let args: Vec<&str> = words.collect();
+    if name == "answer" {
+        return Ok(CodeBlock::Answer(ctx.show_answers.then_some(literal)));
+    }
+    if let Some(title) = directives::admonition_title(name, &args) {
+        return Ok(CodeBlock::Admonition {
+            title,
+            body: directives::expand_containers(literal, &[]),
+        });
+    }
+    if directives::render(name, &args, literal, ctx)?.is_some() {
+        return Err(anyhow!(
+            "The {name} block can't be converted to {format}, use HTML or PDF output instead"
+        ));
+    }
+
+    Ok(CodeBlock::Code(Some(name)))
+}
+
+/// Whether a piece of inline HTML is the blank left in place of a hidden answer.
+pub fn is_answer_blank(html: &str) -> bool {
+    html.starts_with("<span") && html.contains("answer-blank")
+}
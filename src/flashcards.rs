@@ -0,0 +1,130 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use comrak::markdown_to_html;
+
+// Printable area in millimetres, which fits both Letter and A4 with 15mm margins
+const AREA_WIDTH: f64 = 180.0;
+const AREA_HEIGHT: f64 = 245.0;
+
+struct Card {
+    front: String,
+    back: String,
+}
+
+/// Render flashcards from a card list, with each page of fronts followed by a page of backs.
+///
+/// Backs are mirrored left to right so they line up with their fronts when printed double-sided
+/// (flipping on the long edge).
+pub fn generate_html(
+    cards_path: &Path,
+    columns: usize,
+    rows: usize,
+    markdown: &comrak::Options,
+) -> Result<String> {
+    if columns == 0 || rows == 0 {
+        return Err(anyhow!("columns and rows must be at least 1"));
+    }
+    let cards = load_cards(cards_path, markdown)?;
+    if cards.is_empty() {
+        return Err(anyhow!("No cards found in {}", cards_path.display()));
+    }
+
+    let mut body = String::new();
+    for sheet in cards.chunks(columns * rows) {
+        let mut fronts = String::new();
+        let mut backs = String::new();
+        for row in sheet.chunks(columns) {
+            fronts.push_str("<tr>");
+            backs.push_str("<tr>");
+            // Pad short rows so the mirrored backs keep their columns
+            let padding = columns - row.len();
+            for card in row {
+                write!(fronts, "<td>{}</td>", card.front)?;
+            }
+            fronts.push_str(&"<td class=\"flashcard-empty\"></td>".repeat(padding));
+            backs.push_str(&"<td class=\"flashcard-empty\"></td>".repeat(padding));
+            for card in row.iter().rev() {
+                write!(backs, "<td>{}</td>", card.back)?;
+            }
+            fronts.push_str("</tr>\n");
+            backs.push_str("</tr>\n");
+        }
+        write!(
+            body,
+            "<table class=\"flashcards\">\n{fronts}</table>\n\
+             <table class=\"flashcards flashcards-back\">\n{backs}</table>\n"
+        )?;
+    }
+
+    Ok(format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <style>
+        @page {{ margin: 15mm; }}
+        body {{ margin: 0; font-family: sans-serif; }}
+        table.flashcards {{ border-collapse: collapse; table-layout: fixed; page-break-after: always; }}
+        table.flashcards:last-child {{ page-break-after: auto; }}
+        table.flashcards td {{
+            width: {:.1}mm; height: {:.1}mm; padding: 4mm;
+            border: 1px dashed #888; text-align: center; vertical-align: middle; overflow: hidden;
+        }}
+        table.flashcards td.flashcard-empty {{ border-color: transparent; }}
+        table.flashcards:not(.flashcards-back) td {{ font-size: 1.4em; font-weight: bold; }}
+    </style>
+</head>
+<body>
+{body}</body>
+</html>"#,
+        AREA_WIDTH / columns as f64 - 8.0,
+        AREA_HEIGHT / rows as f64 - 8.0,
+    ))
+}
+
+/// Load cards from a CSV file (front and back in the first two columns, after a header row), or
+/// from markdown with a `front: back` card on each line.
+fn load_cards(path: &Path, markdown: &comrak::Options) -> Result<Vec<Card>> {
+    let is_csv = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+    let sides: Vec<(String, String)> = if is_csv {
+        let mut reader = csv::Reader::from_path(path)
+            .context(format!("Failed to open cards: {}", path.display()))?;
+        reader
+            .records()
+            .map(|record| {
+                let record =
+                    record.context(format!("Failed to parse cards: {}", path.display()))?;
+                match (record.get(0), record.get(1)) {
+                    (Some(front), Some(back)) => Ok((front.to_string(), back.to_string())),
+                    _ => Err(anyhow!("Expected front and back columns in {record:?}")),
+                }
+            })
+            .collect::<Result<_>>()?
+    } else {
+        fs::read_to_string(path)
+            .context(format!("Failed to read cards: {}", path.display()))?
+            .lines()
+            .map(|line| line.trim().trim_start_matches(['-', '*']).trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let (front, back) = line
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("Expected `front: back`, got {line:?}"))?;
+                Ok((front.trim().to_string(), back.trim().to_string()))
+            })
+            .collect::<Result<_>>()?
+    };
+
+    Ok(sides
+        .into_iter()
+        .map(|(front, back)| Card {
+            front: markdown_to_html(&front, markdown),
+            back: markdown_to_html(&back, markdown),
+        })
+        .collect())
+}
@@ -0,0 +1,85 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::assets::{self, AssetMode};
+use crate::config::Options;
+
+/// A font to make available to the stylesheet.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum FontSpec {
+    /// A Google Fonts family, downloaded at build time
+    Google {
+        google: String,
+        /// Weights to download (just the default weight if empty)
+        #[serde(default)]
+        weights: Vec<u16>,
+    },
+    /// A local font file
+    File {
+        family: String,
+        file: PathBuf,
+        weight: Option<String>,
+        style: Option<String>,
+    },
+}
+
+/// `@font-face` rules for the configured fonts, with font files handled like other assets.
+pub fn font_face_css(options: &Options) -> Result<String> {
+    let mut css = String::new();
+    for font in &options.fonts {
+        let font_css = match font {
+            FontSpec::Google { google, weights } => google_font_css(google, weights, options)?,
+            FontSpec::File {
+                family,
+                file,
+                weight,
+                style,
+            } => {
+                let mut rule = format!(
+                    "@font-face {{ font-family: \"{family}\"; src: url(\"{}\");",
+                    file.display()
+                );
+                if let Some(weight) = weight {
+                    rule.push_str(&format!(" font-weight: {weight};"));
+                }
+                if let Some(style) = style {
+                    rule.push_str(&format!(" font-style: {style};"));
+                }
+                rule.push_str(" }\n");
+                rule
+            }
+        };
+        css.push_str(&assets::rewrite_css_urls(
+            &font_css,
+            Path::new("."),
+            AssetMode::for_options(options),
+            &options.warnings,
+        )?);
+    }
+    Ok(css)
+}
+
+// Google's stylesheet for the family, with the font files downloaded into the cache
+fn google_font_css(family: &str, weights: &[u16], options: &Options) -> Result<String> {
+    let mut url = format!(
+        "https://fonts.googleapis.com/css2?family={}",
+        family.replace(' ', "+")
+    );
+    if !weights.is_empty() {
+        let weights: Vec<String> = weights.iter().map(u16::to_string).collect();
+        url.push_str(&format!(":wght@{}", weights.join(";")));
+    }
+
+    let stylesheet = assets::fetch_cached(&url, &options.cache_dir)
+        .context(format!("Failed to get font {family:?} from Google Fonts"))?;
+    let css = fs::read_to_string(&stylesheet)
+        .context(format!("Failed to read {}", stylesheet.display()))?;
+    let mode = AssetMode::Resolve {
+        cache_dir: &options.cache_dir,
+    };
+    assets::rewrite_css_urls(&css, Path::new("."), mode, &options.warnings)
+}
@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::config::{MarkdownSettings, PageSettings, TextDirection};
+
+// Per-page settings from a YAML block at the top of a markdown file
+#[derive(Debug, Default, Deserialize)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    pub stylesheet: Option<PathBuf>,
+    /// Extra HTML for the document's `<head>`
+    pub head_html: Option<String>,
+    /// Language and text direction of the page, if they differ from the document's
+    pub lang: Option<String>,
+    pub dir: Option<TextDirection>,
+    /// Where the page goes among those matched by a page pattern, lowest first
+    #[serde(alias = "order")]
+    pub weight: Option<i64>,
+    pub paper_size: Option<String>,
+    #[serde(default)]
+    pub page: PageSettings,
+    #[serde(default)]
+    pub markdown: MarkdownSettings,
+    // Anything else is available as a template variable
+    #[serde(flatten)]
+    pub variables: BTreeMap<String, serde_yaml::Value>,
+}
+
+/// Whether a page path means standard input, `-`.
+pub fn is_stdin(page_path: &Path) -> bool {
+    page_path == Path::new("-")
+}
+
+/// Read a markdown page, separating its front matter from the content.
+///
+/// A page named `-` is read from standard input.
+pub fn read_page(page_path: &Path) -> Result<(FrontMatter, String)> {
+    let markdown_content = if is_stdin(page_path) {
+        read_stdin()?
+    } else {
+        fs::read_to_string(page_path).context(format!(
+            "Failed to read markdown file: {}",
+            page_path.display()
+        ))?
+    };
+
+    let Some((yaml, content)) = split_front_matter(&markdown_content) else {
+        return Ok((FrontMatter::default(), markdown_content));
+    };
+    let mut front_matter: FrontMatter = serde_yaml::from_str(yaml).context(format!(
+        "Failed to parse front matter in {}",
+        page_path.display()
+    ))?;
+
+    // Paths in front matter are relative to the page
+    let page_dir = page_path.parent().unwrap_or_else(|| Path::new("."));
+    front_matter.stylesheet = front_matter.stylesheet.map(|s| page_dir.join(s));
+
+    Ok((front_matter, content.to_string()))
+}
+
+// Pages are read for the worksheet, its answer key, and so on, but stdin can only be read once
+fn read_stdin() -> Result<String> {
+    static STDIN: OnceLock<String> = OnceLock::new();
+    if let Some(content) = STDIN.get() {
+        return Ok(content.clone());
+    }
+    let mut content = String::new();
+    io::stdin()
+        .read_to_string(&mut content)
+        .context("Failed to read markdown from stdin")?;
+    Ok(STDIN.get_or_init(|| content).clone())
+}
+
+/// Split `---` delimited front matter from the start of a document.
+fn split_front_matter(markdown: &str) -> Option<(&str, &str)> {
+    let rest = markdown
+        .strip_prefix("---\n")
+        .or_else(|| markdown.strip_prefix("---\r\n"))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+
+    None
+}
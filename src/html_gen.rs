@@ -1,91 +1,688 @@
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::LazyLock;
 
-use anyhow::{Context, Result};
-use comrak::{Options, markdown_to_html};
-use lol_html::html_content::Element;
+use anyhow::{Context, Result, anyhow};
+use comrak::nodes::{NodeHtmlBlock, NodeValue};
+use comrak::{Arena, Plugins, format_html_with_plugins, markdown_to_html, parse_document};
+use lol_html::html_content::{ContentType, Element};
 use lol_html::{RewriteStrSettings, element, rewrite_str, text};
 use slug::slugify;
 
-// Comrak options can be static since they're configuration
-static COMRAK_OPTIONS: LazyLock<Options> = LazyLock::new(|| {
-    let mut options = Options::default();
+use crate::assets::{self, AssetMode};
+use crate::config::{Numbering, Options, PageSettings, RunningText, SectionSettings};
+use crate::context::RenderContext;
+use crate::directives;
+use crate::fonts;
+use crate::front_matter::read_page;
+use crate::math;
+use crate::page::Page;
+use crate::title_page;
 
-    // Enable common extensions
-    options.extension.table = true;
-    options.extension.strikethrough = true;
-    options.extension.tasklist = true;
-    options.extension.superscript = true;
-    options.extension.footnotes = true;
-    options.extension.description_lists = true;
+// Styles the generator relies on, emitted before the user stylesheet so they can be overridden
+const BASE_CSS: &str = r#"
+        .answer { color: #c0392b; font-weight: bold; }
+        span.answer-blank { display: inline-block; min-width: 4em; border-bottom: 1px solid; }
+        div.answer-blank { min-height: 4em; }
+        table.drill { width: 100%; border-collapse: separate; border-spacing: 1.5em 1em; }
+        td.drill-problem { font-size: 1.3em; text-align: right; vertical-align: top; }
+        .drill-number { float: left; font-size: 0.7em; }
+        .drill-left, .drill-right { display: block; }
+        .drill-answer { display: block; min-height: 1.3em; border-top: 2px solid; }
+        .word-bank { border: 2px solid #000; padding: 0.5em 1em; margin: 1em 0; text-align: center; }
+        .word-bank-word { display: inline-block; margin: 0 1em; }
+        .choice-question { margin: 1em 0; break-inside: avoid; }
+        .choice-stem > p:first-of-type { display: inline; }
+        .choice-number { font-weight: bold; margin-right: 0.3em; }
+        .choices { margin-left: 1.5em; }
+        .choice { margin: 0.2em 0; }
+        .choice-letter { display: inline-block; width: 1.4em; height: 1.4em; line-height: 1.4em; border: 1px solid; border-radius: 50%; text-align: center; margin-right: 0.4em; font-size: 0.85em; }
+        table.matching { width: 100%; border-spacing: 0.5em 0.8em; }
+        td.matching-blank { width: 3em; }
+        td.matching-blank .answer-blank { min-width: 2em; }
+        td.matching-term { width: 35%; }
+        div.page[data-title] { string-set: title attr(data-title); }
+        .watermark {
+            position: fixed; top: 50%; left: 50%; z-index: 1000;
+            transform: translate(-50%, -50%) rotate(-45deg);
+            font-size: 96pt; font-weight: bold; white-space: nowrap;
+            color: rgba(0, 0, 0, 0.1); pointer-events: none;
+        }
+        .variant-label { float: right; font-weight: bold; }
+        .title-page { padding-top: 25%; text-align: center; }
+        .title-page-logo { max-width: 40%; max-height: 5cm; }
+        .title-page-title { font-size: 36pt; }
+        .title-page-subtitle { font-size: 18pt; }
+        .admonition { border-left: 4px solid; border-radius: 4px; padding: 0.2em 1em; margin: 1em 0; break-inside: avoid; }
+        .admonition-title { font-weight: bold; margin: 0.5em 0; }
+        .admonition-title::before { margin-right: 0.4em; }
+        .admonition-note { border-color: #2980b9; background: #eaf2f8; }
+        .admonition-note .admonition-title::before { content: "ℹ️"; }
+        .admonition-hint, .admonition-tip { border-color: #27ae60; background: #e9f7ef; }
+        .admonition-hint .admonition-title::before, .admonition-tip .admonition-title::before { content: "💡"; }
+        .admonition-warning { border-color: #e67e22; background: #fdf2e9; }
+        .admonition-warning .admonition-title::before { content: "⚠️"; }
+        table.csv-table { border-collapse: collapse; margin: 1em 0; }
+        table.csv-table th, table.csv-table td { border: 1px solid #666; padding: 0.2em 0.6em; }
+        table.csv-table th { background: #eee; }
+        .code-listing { display: flex; align-items: flex-start; margin: 1em 0; break-inside: avoid; }
+        .code-listing > pre { margin: 0; }
+        .code-listing > pre:last-child { flex: 1; overflow-x: auto; }
+        pre.line-numbers { text-align: right; color: #999; padding-right: 0.6em; margin-right: 0.6em; border-right: 1px solid #ccc; user-select: none; }
+        .heading-anchor { margin-left: 0.3em; color: #999; text-decoration: none; visibility: hidden; }
+        .heading-anchor::before { content: '#'; }
+        h1:hover .heading-anchor, h2:hover .heading-anchor, h3:hover .heading-anchor,
+        h4:hover .heading-anchor, h5:hover .heading-anchor, h6:hover .heading-anchor { visibility: visible; }
+        @media print { .heading-anchor { display: none; } }
+        .toc ul { list-style: none; padding: 0; }
+        .toc li.toc-level-2 { margin-left: 1.5em; }
+        [dir="rtl"] .choice-number { margin-right: 0; margin-left: 0.3em; }
+        [dir="rtl"] .choices { margin-left: 0; margin-right: 1.5em; }
+        [dir="rtl"] .choice-letter { margin-right: 0; margin-left: 0.4em; }
+        [dir="rtl"] .variant-label { float: left; }
+        [dir="rtl"] .admonition { border-left: none; border-right: 4px solid; }
+        [dir="rtl"] .admonition-title::before { margin-right: 0; margin-left: 0.4em; }
+        [dir="rtl"] .heading-anchor { margin-left: 0; margin-right: 0.3em; }
+        [dir="rtl"] .toc li.toc-level-2 { margin-left: 0; margin-right: 1.5em; }
+        [dir="rtl"] pre, [dir="rtl"] .katex { direction: ltr; text-align: left; }
+        .toc a { color: inherit; text-decoration: none; }
+        .toc a::after { content: leader('.') target-counter(attr(href), page); }
+        .answer-key-separator { padding-top: 40%; text-align: center; font-size: 48pt; font-weight: bold; text-transform: uppercase; }
+        svg.coordplane { display: block; max-width: 100%; margin: 1em auto; }
+        table.crossword-grid { border-collapse: collapse; margin: 1em auto; }
+        table.crossword-grid td { width: 1.8em; height: 1.8em; padding: 0; }
+        td.crossword-cell { border: 1px solid #000; position: relative; text-align: center; vertical-align: middle; }
+        .crossword-number { position: absolute; top: 1px; left: 2px; font-size: 0.55em; }
+        .crossword-clues { display: flex; gap: 2em; }
+        .crossword-clues > div { flex: 1; }
+        table.sudoku { border-collapse: collapse; border: 3px solid #000; margin: 1em auto; }
+        table.sudoku td { width: 2em; height: 2em; border: 1px solid #666; text-align: center; font-size: 1.3em; }
+        table.sudoku td.sudoku-given { font-weight: bold; }
+        table.sudoku tr.sudoku-box-top td { border-top: 3px solid #000; }
+        table.sudoku td.sudoku-box-left { border-left: 3px solid #000; }
+        table.times-table { border-collapse: collapse; margin: 1em auto; }
+        table.times-table th, table.times-table td { width: 2em; height: 2em; border: 1px solid #000; text-align: center; }
+        table.times-table th { background: #eee; }
+        table.hundred-chart { border-collapse: collapse; margin: 1em auto; }
+        table.hundred-chart td { width: 2.2em; height: 2.2em; border: 1px solid #000; text-align: center; }
+        td.hundred-chart-highlight { background: #fde9a9; }
+        table.bingo { border-collapse: collapse; margin: 1em auto; table-layout: fixed; }
+        table.bingo th { font-size: 2em; }
+        table.bingo td { border: 2px solid #000; text-align: center; vertical-align: middle; overflow-wrap: break-word; }
+        table.bingo-1-up td { width: 3.2cm; height: 3.2cm; font-size: 1.4em; }
+        table.bingo-2-up td { width: 2.2cm; height: 2.2cm; }
+        td.bingo-free { font-weight: bold; background: #eee; }
+        .bingo-calls ol { columns: 4; }
+        table.clocks { width: 100%; }
+        td.clock { text-align: center; padding: 0.5em; }
+        svg.clock-face { width: 1.6in; height: 1.6in; }
+        .clock-label { margin-top: 0.3em; font-size: 1.2em; }
+        .fraction-model svg { display: inline-block; vertical-align: middle; margin: 0 2mm; }
+        svg.fraction-circle { width: 2.5cm; height: 2.5cm; }
+        svg.fraction-bar { width: 6cm; height: 1.2cm; }
+        svg.fraction-number-line { width: 10cm; }
+        svg.grid { display: block; max-width: 100%; margin: 1em auto; }
+        svg.handwriting-row { display: block; max-width: 100%; margin-bottom: 2mm; }
+        table.money { width: 100%; border-spacing: 1em; }
+        td.money-problem { vertical-align: top; }
+        .money-items svg { vertical-align: middle; margin: 1mm; }
+        .money-total { margin-top: 0.5em; font-size: 1.2em; }
+        .graphviz-diagram svg { display: block; max-width: 100%; height: auto; margin: 1em auto; }
+        .mermaid-diagram svg { display: block; max-width: 100%; height: auto; margin: 1em auto; }
+        svg.maze { display: block; width: 100%; max-height: 8in; margin: 1em auto; }
+"#;
 
-    options.render.unsafe_ = true;
-    options.render.hardbreaks = false;
-    options.render.github_pre_lang = true;
+pub fn generate_html(options: &Options, show_answers: bool) -> Result<String> {
+    let mut ctx = RenderContext::new(options, show_answers);
+    let (mut html_body, mut page_css) = generate_html_body(options, &mut ctx)?;
+    // Heading text is already escaped
+    let title = match &ctx.title {
+        Some(title) => escape_html(title),
+        None => find_headings(&html_body)?
+            .into_iter()
+            .find(|heading| heading.level == 1)
+            .map(|heading| heading.text.trim().to_string())
+            .unwrap_or_default(),
+    };
+    if options.toc {
+        // Only the worksheet's own headings, not the title page or answer key
+        let toc = table_of_contents(&html_body)?;
+        html_body = format!(r#"{toc}<div class="page-break"></div>{html_body}"#);
+    }
+    if options.combined_answer_key && !show_answers {
+        // Rendered from the same seed, so randomized content matches the student pages. The
+        // headings are in the same document, so their ids have to differ from the student's.
+        let mut key_ctx = RenderContext::new(options, true);
+        key_ctx.heading_ids = ctx.heading_ids.clone();
+        let (key_body, key_css) = generate_html_body(options, &mut key_ctx)?;
+        html_body.push_str(concat!(
+            r#"<div class="page-break"></div>"#,
+            r#"<div class="answer-key-separator">Answer Key</div>"#,
+            r#"<div class="page-break"></div>"#,
+        ));
+        html_body.push_str(&key_body);
+        page_css.push_str(&key_css);
+    }
+    if let Some(title_page) = &options.title_page {
+        let cover = title_page::render(title_page, options)?;
+        html_body = format!(r#"{cover}<div class="page-break"></div>{html_body}"#);
+    }
+    let css_content = load_stylesheets(options)?;
+    let font_css = fonts::font_face_css(options)?;
+    let numbering_css = options
+        .numbering
+        .as_ref()
+        .map(numbering_css)
+        .unwrap_or_default();
+    let page_rule = format!(
+        "@page {{ {}{}{}}}",
+        options.page.declarations(),
+        margin_boxes("top", &options.header),
+        margin_boxes("bottom", &options.footer)
+    );
+    let math_css = if html_body.contains("class=\"katex") {
+        math::katex_css(options)?
+    } else {
+        String::new()
+    };
+    // Fixed elements are repeated on every printed page
+    let watermark = match &options.watermark {
+        Some(text) => format!("<div class=\"watermark\">{}</div>\n", escape_html(text)),
+        None => String::new(),
+    };
+    let variant_label = match &options.variant {
+        Some(variant) => format!("<div class=\"variant-label\">Form {variant}</div>\n"),
+        None => String::new(),
+    };
 
-    options
-});
+    let styles = format!(
+        r#"{font_css}{math_css}{BASE_CSS}
+        {numbering_css}
+        {page_rule}
+        {css_content}
+        {page_css}
+        @media print {{
+            .page-break {{ page-break-before: always; }}
+        }}
+    "#
+    );
+    let body = format!("{watermark}{variant_label}{html_body}");
+    let head_html = options.head_html.as_deref().unwrap_or_default();
+    let head: Vec<&str> = [head_html.trim(), ctx.head_html.trim()]
+        .into_iter()
+        .filter(|html| !html.is_empty())
+        .collect();
+    let head = head.join("\n    ");
 
-pub fn generate_html(page_paths: &[PathBuf], stylesheet_path: Option<&Path>) -> Result<String> {
-    let html_body = generate_html_body(page_paths)?;
-    let css_content = load_stylesheet(stylesheet_path)?;
+    let html_attributes = html_attributes(options);
+    let full_html = match &options.document_template {
+        Some(template) => {
+            render_document_template(template, &title, &head, &styles, &body, options)?
+        }
+        None => format!(
+            r#"<!DOCTYPE html>
+<html{html_attributes}>
+<head>
+    <meta charset="UTF-8">
+    <title>{title}</title>
+    {head}
+    <style>{styles}</style>
+</head>
+<body>
+{body}
+</body>
+</html>"#
+        ),
+    };
+
+    // Image paths were already made absolute for each page
+    let mode = AssetMode::for_options(options);
+    assets::rewrite_images(&full_html, Path::new("."), mode, &options.warnings)
+}
+
+// Fill in a user's document template. `title` is the escaped document title, `head` holds
+// extra tags for the `<head>`, `styles` the contents of the `<style>` element, and `body` the
+// worksheet itself.
+fn render_document_template(
+    path: &Path,
+    title: &str,
+    head: &str,
+    styles: &str,
+    body: &str,
+    options: &Options,
+) -> Result<String> {
+    let template = fs::read_to_string(path).context(format!(
+        "Failed to read document template: {}",
+        path.display()
+    ))?;
+    let mut context = tera::Context::new();
+    for (name, value) in &options.variables {
+        context.insert(name, value);
+    }
+    context.insert("lang", &options.lang);
+    context.insert("dir", &options.dir.map(|dir| dir.name()));
+    context.insert("title", title);
+    context.insert("head", head);
+    context.insert("styles", styles);
+    context.insert("body", body);
+    tera::Tera::one_off(&template, &context, false).context(format!(
+        "Failed to render document template {}",
+        path.display()
+    ))
+}
+
+/// Render each page as its own HTML file in `output_dir`, plus an `index.html` linking them.
+///
+/// Returns the path and HTML of each file.
+pub fn generate_split_html(
+    options: &Options,
+    output_dir: &Path,
+    show_answers: bool,
+) -> Result<Vec<(PathBuf, String)>> {
+    let mut files = vec![];
+    let mut links = String::new();
+    for page_path in &options.pages {
+        let stem = page_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let file_name = format!("{stem}.html");
+        let page_options = Options {
+            pages: vec![page_path.clone()],
+            output_file: output_dir.join(&file_name),
+            // These belong on the index, not on every page
+            title_page: None,
+            toc: false,
+            ..options.clone()
+        };
+        files.push((
+            page_options.output_file.clone(),
+            generate_html(&page_options, show_answers)?,
+        ));
 
-    let full_html = format!(
+        let (mut front_matter, _) = read_page(page_path)?;
+        if let Some(settings) = options.page_settings.get(page_path) {
+            settings.apply(&mut front_matter);
+        }
+        let title = front_matter.title.unwrap_or(stem);
+        links.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            escape_attribute(&file_name),
+            escape_html(&title)
+        ));
+    }
+
+    let title = match options.title_page.as_ref().and_then(|t| t.title.as_ref()) {
+        Some(title) => title.clone(),
+        None => output_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    let title = escape_html(&title);
+    let css_content = load_stylesheets(options)?;
+    let html_attributes = html_attributes(options);
+    let index = format!(
         r#"<!DOCTYPE html>
-<html>
+<html{html_attributes}>
 <head>
     <meta charset="UTF-8">
-    <style>
+    <title>{title}</title>
+    <style>{BASE_CSS}
         {css_content}
-        @media print {{
-            .page-break {{ page-break-before: always; }}
-        }}
     </style>
 </head>
 <body>
-{html_body}
+<h1>{title}</h1>
+<ol class="page-index">
+{links}</ol>
 </body>
 </html>"#
     );
+    files.push((output_dir.join("index.html"), index));
+
+    Ok(files)
+}
+
+/// Shrink a finished document by dropping whitespace, comments, and redundant quoting.
+///
+/// Styles are left alone, since the CSS minifier doesn't know the paged media rules the PDF
+/// backends rely on.
+pub fn minify(html: &str) -> String {
+    let cfg = minify_html::Cfg {
+        keep_closing_tags: true,
+        keep_html_and_head_opening_tags: true,
+        minify_css: false,
+        ..minify_html::Cfg::default()
+    };
+    String::from_utf8_lossy(&minify_html::minify(html.as_bytes(), &cfg)).into_owned()
+}
+
+// The `lang` and `dir` attributes for the `<html>` element
+fn html_attributes(options: &Options) -> String {
+    let mut attributes = String::new();
+    if let Some(lang) = &options.lang {
+        attributes.push_str(&format!(r#" lang="{}""#, escape_attribute(lang)));
+    }
+    if let Some(dir) = options.dir {
+        attributes.push_str(&format!(r#" dir="{}""#, dir.name()));
+    }
+    attributes
+}
+
+// `@page` margin boxes for a running header or footer
+fn margin_boxes(edge: &str, text: &RunningText) -> String {
+    [
+        ("left", &text.left),
+        ("center", &text.center),
+        ("right", &text.right),
+    ]
+    .into_iter()
+    .filter_map(|(position, text)| {
+        let content = css_content(text.as_ref()?, RUNNING_TEXT_PLACEHOLDERS);
+        Some(format!("@{edge}-{position} {{ content: {content}; }} "))
+    })
+    .collect()
+}
+
+const RUNNING_TEXT_PLACEHOLDERS: &[(&str, &str)] = &[
+    ("{page}", "counter(page)"),
+    ("{pages}", "counter(pages)"),
+    ("{title}", "string(title)"),
+];
+
+const NUMBERING_PLACEHOLDERS: &[(&str, &str)] = &[
+    ("{1}", "counter(heading-1)"),
+    ("{2}", "counter(heading-2)"),
+    ("{3}", "counter(heading-3)"),
+    ("{4}", "counter(heading-4)"),
+    ("{5}", "counter(heading-5)"),
+    ("{6}", "counter(heading-6)"),
+    ("{q}", "counter(question)"),
+];
+
+// A CSS `content` value, with placeholders replaced by their CSS values
+fn css_content(text: &str, placeholders: &[(&str, &str)]) -> String {
+    let mut parts = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let placeholder = placeholders
+            .iter()
+            .find(|(placeholder, _)| rest[start..].starts_with(placeholder));
+        let Some((placeholder, value)) = placeholder else {
+            parts.push(css_string(&rest[..=start]));
+            rest = &rest[start + 1..];
+            continue;
+        };
+        parts.push(css_string(&rest[..start]));
+        parts.push(value.to_string());
+        rest = &rest[start + placeholder.len()..];
+    }
+    parts.push(css_string(rest));
+    parts.retain(|part| part != "\"\"");
+    if parts.is_empty() {
+        "\"\"".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+// CSS counters for numbering headings and questions. Every heading level is counted, so
+// formats can refer to levels that aren't numbered themselves.
+fn numbering_css(numbering: &Numbering) -> String {
+    let resets_questions = |level: u8| {
+        numbering
+            .questions
+            .as_ref()
+            .is_some_and(|format| format.contains(&format!("{{{level}}}")))
+    };
+
+    let mut css = String::from(
+        "body { counter-reset: heading-1 heading-2 heading-3 heading-4 heading-5 heading-6 question; }\n",
+    );
+    for level in 1..=6u8 {
+        let mut resets: Vec<String> = (level + 1..=6).map(|l| format!("heading-{l}")).collect();
+        if resets_questions(level) {
+            resets.push("question".to_string());
+        }
+        css.push_str(&format!(
+            "div.page h{level} {{ counter-increment: heading-{level}; "
+        ));
+        if !resets.is_empty() {
+            css.push_str(&format!("counter-reset: {}; ", resets.join(" ")));
+        }
+        css.push_str("}\n");
+        if let Some(format) = numbering.headings.get(&level) {
+            let content = css_content(format, NUMBERING_PLACEHOLDERS);
+            css.push_str(&format!(
+                "div.page h{level}::before {{ content: {content}; }}\n"
+            ));
+        }
+    }
+    if let Some(format) = &numbering.questions {
+        let content = css_content(format, NUMBERING_PLACEHOLDERS);
+        css.push_str(&format!(
+            ".choice-question {{ counter-increment: question; }}\n\
+             .choice-number {{ display: none; }}\n\
+             .choice-stem::before {{ content: {content}; font-weight: bold; margin-right: 0.3em; }}\n"
+        ));
+    }
+    css
+}
 
-    Ok(full_html)
+fn css_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
 }
 
-fn generate_html_body(page_paths: &[PathBuf]) -> Result<String> {
+// Returns the body HTML, and any CSS needed for per-page settings
+pub fn generate_html_body(options: &Options, ctx: &mut RenderContext) -> Result<(String, String)> {
     let mut all_html_content = String::new();
+    let mut page_css = String::new();
 
-    for (i, page_path) in page_paths.iter().enumerate() {
-        let markdown_content = fs::read_to_string(page_path).context(format!(
-            "Failed to read markdown file: {}",
-            page_path.display()
-        ))?;
+    for (i, page_path) in options.pages.iter().enumerate() {
+        let Page {
+            front_matter,
+            markdown,
+        } = Page::load(page_path, options, ctx)?;
 
-        let generated_html = markdown_to_html(&markdown_content, &COMRAK_OPTIONS);
-        let final_html = add_section_wrappers_to_html(&generated_html)?;
+        let generated_html = render_markdown(&markdown, ctx)?;
+        let page_dir = page_path.parent().unwrap_or_else(|| Path::new("."));
+        let mode = AssetMode::Resolve {
+            cache_dir: &options.cache_dir,
+        };
+        let generated_html =
+            assets::rewrite_images(&generated_html, page_dir, mode, &options.warnings)?;
+        ctx.images
+            .extend(assets::local_images(&generated_html, &options.cache_dir)?);
+        let generated_html = add_heading_ids(&generated_html, options.heading_anchors, ctx)?;
+        let final_html = add_section_wrappers_to_html(&generated_html, &options.sections)?;
 
         if i > 0 {
             all_html_content.push_str(r#"<div class="page-break"></div>"#);
         }
 
-        all_html_content.push_str(&final_html);
+        // Each page gets a named CSS page so its page settings can differ from the rest
+        let page_name = format!("page-{}", i + 1);
+        let mut page_attributes =
+            format!(r#" class="page" data-page="{page_name}" style="page: {page_name}""#);
+        let page_settings = PageSettings {
+            size: front_matter.page.size.or(front_matter.paper_size),
+            ..front_matter.page
+        };
+        if !page_settings.declarations().is_empty() {
+            // Merged with the defaults so e.g. an orientation keeps the configured size
+            let declarations = page_settings.or(&options.page).declarations();
+            page_css.push_str(&format!("@page {page_name} {{ {declarations}}}\n"));
+        }
+        if let Some(title) = &front_matter.title {
+            page_attributes.push_str(&format!(r#" data-title="{}""#, escape_attribute(title)));
+            if ctx.title.is_none() {
+                ctx.title = Some(title.clone());
+            }
+        }
+        if let Some(lang) = &front_matter.lang {
+            page_attributes.push_str(&format!(r#" lang="{}""#, escape_attribute(lang)));
+        }
+        if let Some(dir) = front_matter.dir {
+            page_attributes.push_str(&format!(r#" dir="{}""#, dir.name()));
+        }
+        if let Some(stylesheet) = &front_matter.stylesheet {
+            let css = load_stylesheet(Some(stylesheet), options)?;
+            let selector = format!(r#".page[data-page="{page_name}"]"#);
+            page_css.push_str(&scope_css(&css, &selector)?);
+            page_css.push('\n');
+        }
+        // Pages often share tags, which only need to be included once
+        if let Some(head_html) = &front_matter.head_html
+            && !ctx.head_html.contains(head_html.as_str())
+        {
+            ctx.head_html.push_str(head_html);
+            ctx.head_html.push('\n');
+        }
+
+        all_html_content.push_str(&format!("<div{page_attributes}>\n{final_html}\n</div>"));
+    }
+    ctx.log_render();
+
+    Ok((all_html_content, page_css))
+}
+
+// Nest a stylesheet's rules under `selector`, so they only apply to matching elements
+fn scope_css(css: &str, selector: &str) -> Result<String> {
+    if css.trim().is_empty() {
+        return Ok(String::new());
+    }
+    grass::from_string(
+        format!("{selector} {{\n{css}\n}}"),
+        &grass::Options::default(),
+    )
+    .map_err(|e| anyhow!("Failed to apply page stylesheet: {e}"))
+}
+
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+pub fn render_markdown(markdown: &str, ctx: &mut RenderContext) -> Result<String> {
+    let arena = Arena::new();
+    // Cloned since rendering directives needs the context
+    let mut markdown_options = ctx.markdown.clone();
+    let raw_html = markdown_options.render.unsafe_;
+    let root = parse_document(&arena, markdown, &markdown_options);
+
+    // Replace fenced blocks we know how to render, and math, with their generated HTML
+    for node in root.descendants() {
+        let replacement = match &node.data.borrow().value {
+            NodeValue::CodeBlock(block) => render_code_block(&block.info, &block.literal, ctx)?
+                .map(|literal| {
+                    NodeValue::HtmlBlock(NodeHtmlBlock {
+                        block_type: 0,
+                        literal,
+                    })
+                }),
+            NodeValue::Math(math) => Some(NodeValue::HtmlInline(math::render(
+                &math.literal,
+                math.display_math,
+                ctx.math,
+            )?)),
+            // With raw HTML turned off it's shown as text, so generated HTML can still get through
+            NodeValue::HtmlBlock(block) if !raw_html => {
+                Some(NodeValue::Text(block.literal.clone()))
+            }
+            NodeValue::HtmlInline(html) if !raw_html => Some(NodeValue::Text(html.clone())),
+            _ => None,
+        };
+        if let Some(value) = replacement {
+            node.data.borrow_mut().value = value;
+        }
+    }
+
+    let mut plugins = Plugins::default();
+    if let Some(highlighter) = &ctx.highlighter {
+        plugins.render.codefence_syntax_highlighter = Some(highlighter);
     }
 
-    Ok(all_html_content)
+    markdown_options.render.unsafe_ = true;
+    let mut html = vec![];
+    format_html_with_plugins(root, &markdown_options, &mut html, &plugins)?;
+    Ok(String::from_utf8(html)?)
+}
+
+/// Render a fenced code block's answer or directive, or `None` for an ordinary code block.
+pub fn render_code_block(
+    info: &str,
+    literal: &str,
+    ctx: &mut RenderContext,
+) -> Result<Option<String>> {
+    let mut words = info.split_whitespace();
+    let Some(name) = words.next() else {
+        return Ok(None);
+    };
+    let html = match name {
+        "answer" if ctx.show_answers => format!(
+            "<div class=\"answer\">\n{}</div>\n",
+            markdown_to_html(literal, &ctx.markdown)
+        ),
+        "answer" => "<div class=\"answer-blank\"></div>\n".to_string(),
+        _ => {
+            let args: Vec<&str> = words.collect();
+            return directives::render(name, &args, literal, ctx);
+        }
+    };
+    Ok(Some(html))
+}
+
+// The theme's custom properties and built-in theme, then the configured stylesheets
+// concatenated in order so later ones can override earlier ones
+fn load_stylesheets(options: &Options) -> Result<String> {
+    let mut css = String::new();
+    if !options.theme.is_empty() {
+        css.push_str(":root {");
+        for (name, value) in &options.theme {
+            css.push_str(&format!(" --{name}: {value};"));
+        }
+        css.push_str(" }\n");
+    }
+    if let Some(builtin_theme) = options.builtin_theme {
+        css.push_str(builtin_theme.css());
+    }
+    for stylesheet in &options.stylesheets {
+        css.push_str(&load_stylesheet(Some(stylesheet), options)?);
+        css.push('\n');
+    }
+    Ok(css)
 }
 
-fn load_stylesheet(stylesheet_path: Option<&Path>) -> Result<String> {
+fn load_stylesheet(stylesheet_path: Option<&Path>, options: &Options) -> Result<String> {
     if let Some(stylesheet_path) = stylesheet_path {
         if stylesheet_path.exists() {
-            fs::read_to_string(stylesheet_path).context(format!(
-                "Failed to read stylesheet: {}",
-                stylesheet_path.display()
-            ))
+            let css = if stylesheet_path.extension().is_some_and(|ext| ext == "scss") {
+                grass::from_path(stylesheet_path, &grass::Options::default())
+                    .map_err(|e| anyhow!("Failed to compile {}: {e}", stylesheet_path.display()))?
+            } else {
+                fs::read_to_string(stylesheet_path).context(format!(
+                    "Failed to read stylesheet: {}",
+                    stylesheet_path.display()
+                ))?
+            };
+            // Fonts and images in the stylesheet are relative to it
+            let stylesheet_dir = stylesheet_path.parent().unwrap_or_else(|| Path::new("."));
+            let mode = AssetMode::for_options(options);
+            assets::rewrite_css_urls(&css, stylesheet_dir, mode, &options.warnings)
         } else {
-            eprintln!(
-                "Warning: Stylesheet {} not found, proceeding without styles",
-                stylesheet_path.display()
-            );
+            options.warnings.warn(
+                format!("Stylesheet {} not found", stylesheet_path.display()),
+                "proceeding without styles",
+            )?;
             Ok(String::new())
         }
     } else {
@@ -93,11 +690,21 @@ fn load_stylesheet(stylesheet_path: Option<&Path>) -> Result<String> {
     }
 }
 
-pub fn add_section_wrappers_to_html(html: &str) -> Result<String> {
+/// A heading found in rendered HTML.
+pub struct Heading {
+    // Byte offset of the start tag
+    location: usize,
+    pub level: u8,
+    /// Text content, still HTML-escaped
+    pub text: String,
+    id: Option<String>,
+}
+
+/// Find all the headings and record their locations, levels, and text.
+pub fn find_headings(html: &str) -> Result<Vec<Heading>> {
     let headings = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
     let buffer = std::rc::Rc::new(std::cell::RefCell::new(String::new()));
 
-    // Find all the headings and record their locations, levels, and slugs.
     rewrite_str(
         html,
         RewriteStrSettings {
@@ -109,11 +716,16 @@ pub fn add_section_wrappers_to_html(html: &str) -> Result<String> {
                     let headings = headings.clone();
                     let location = el.source_location().bytes().start;
                     let level = el.tag_name().chars().nth(1).unwrap().to_digit(10).unwrap() as u8;
+                    let id = el.get_attribute("id");
 
                     if let Some(handlers) = el.end_tag_handlers() {
                         handlers.push(Box::new(move |_| {
-                            let slug = slugify(&*buffer.borrow());
-                            headings.borrow_mut().push((location, level, slug));
+                            headings.borrow_mut().push(Heading {
+                                location,
+                                level,
+                                text: buffer.borrow().clone(),
+                                id,
+                            });
                             Ok(())
                         }));
                     }
@@ -129,14 +741,99 @@ pub fn add_section_wrappers_to_html(html: &str) -> Result<String> {
         },
     )?;
 
-    let headings = headings.borrow();
+    Ok(headings.take())
+}
+
+// Give the h1 and h2 headings ids, and return the body with a contents list for them
+fn table_of_contents(html: &str) -> Result<String> {
+    let mut toc = String::from("<nav class=\"toc\">\n<h1>Contents</h1>\n<ul>\n");
+    for heading in find_headings(html)? {
+        if let (1 | 2, Some(id)) = (heading.level, &heading.id) {
+            toc.push_str(&format!(
+                "<li class=\"toc-level-{}\"><a href=\"#{id}\">{}</a></li>\n",
+                heading.level, heading.text
+            ));
+        }
+    }
+    toc.push_str("</ul>\n</nav>\n");
+
+    Ok(toc)
+}
+
+/// Give each heading a unique id based on its text, for links to it.
+///
+/// Headings that already have an id keep it. With `anchors`, each heading also gets a link to
+/// itself.
+pub fn add_heading_ids(html: &str, anchors: bool, ctx: &mut RenderContext) -> Result<String> {
+    let mut ids = vec![];
+    for heading in find_headings(html)? {
+        let id = match heading.id {
+            Some(id) => id,
+            None => {
+                let slug = match slugify(&heading.text) {
+                    slug if slug.is_empty() => "section".to_string(),
+                    slug => slug,
+                };
+                let mut id = slug.clone();
+                let mut n = 1;
+                while ctx.heading_ids.contains(&id) {
+                    n += 1;
+                    id = format!("{slug}-{n}");
+                }
+                id
+            }
+        };
+        ctx.heading_ids.insert(id.clone());
+        ids.push(id);
+    }
+
+    // Headings are found in the same order they're rewritten
+    let mut ids = ids.into_iter();
+    let html = rewrite_str(
+        html,
+        RewriteStrSettings {
+            element_content_handlers: vec![element!(
+                "h1, h2, h3, h4, h5, h6",
+                |el: &mut Element| {
+                    let Some(id) = ids.next() else {
+                        return Ok(());
+                    };
+                    el.set_attribute("id", &id)?;
+                    if anchors {
+                        el.append(
+                            &format!(
+                                r##"<a class="heading-anchor" href="#{}" aria-label="Link to this section"></a>"##,
+                                escape_attribute(&id)
+                            ),
+                            ContentType::Html,
+                        );
+                    }
+                    Ok(())
+                }
+            )],
+            ..RewriteStrSettings::new()
+        },
+    )?;
+    Ok(html)
+}
+
+pub fn add_section_wrappers_to_html(html: &str, settings: &SectionSettings) -> Result<String> {
+    let headings = find_headings(html)?;
+    let tag = settings.tag.name();
 
     // Process headings in order of appearance
     let mut result = String::new();
     let mut header_stack: Vec<(u8, String)> = Vec::new(); // (level, slug)
     let mut last_pos = 0;
 
-    for &(location, level, ref slug) in headings.iter() {
+    for Heading {
+        location,
+        level,
+        text,
+        id,
+    } in headings
+    {
+        let slug = slugify(&text);
         // Add content before this heading
         result.push_str(&html[last_pos..location]);
 
@@ -144,17 +841,26 @@ pub fn add_section_wrappers_to_html(html: &str) -> Result<String> {
         while let Some(&(last_level, _)) = header_stack.last() {
             if last_level >= level {
                 header_stack.pop();
-                result.push_str("</div>");
+                result.push_str(&format!("</{tag}>"));
             } else {
                 break;
             }
         }
 
-        // Open new section before this heading
-        result.push_str(&format!("\n<div class=\"{slug}\">\n"));
+        // Open new section before this heading. Ids have to be unique, so they come from the
+        // heading's id rather than its text.
+        let prefix = &settings.class_prefix;
+        let mut attributes = match (settings.use_id, &id) {
+            (true, Some(id)) => format!(" id=\"{prefix}{}\"", escape_attribute(id)),
+            _ => format!(" class=\"{prefix}{slug}\""),
+        };
+        if settings.data_level {
+            attributes.push_str(&format!(" data-level=\"{level}\""));
+        }
+        result.push_str(&format!("\n<{tag}{attributes}>\n"));
 
         // Update stack
-        header_stack.push((level, slug.clone()));
+        header_stack.push((level, slug));
         last_pos = location;
     }
 
@@ -163,8 +869,8 @@ pub fn add_section_wrappers_to_html(html: &str) -> Result<String> {
 
     // Close any remaining open sections
     if !header_stack.is_empty() {
-        result.push_str(&"</div>\n".repeat(header_stack.len() - 1));
-        result.push_str("</div>"); // Last one without newline
+        result.push_str(&format!("</{tag}>\n").repeat(header_stack.len() - 1));
+        result.push_str(&format!("</{tag}>")); // Last one without newline
     }
 
     Ok(result)
@@ -0,0 +1,261 @@
+mod code;
+mod table;
+
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+use comrak::nodes::{NodeValue, Sourcepos};
+use comrak::{Arena, parse_document};
+
+use crate::directives;
+
+/// Markup like `{{ name "path" key=value }}` that pulls in another file.
+struct FileMarkup<'a> {
+    name: &'a str,
+    path: &'a str,
+    args: Vec<(&'a str, &'a str)>,
+}
+
+// Stands in for code until the rest of the page has been preprocessed. Private use
+// characters won't turn up in a page, and mean nothing to Tera or the inline markup.
+const CODE_PLACEHOLDER: char = '\u{E000}';
+
+/// A page with its file markup expanded, except for code which is set aside.
+pub struct Expanded {
+    pub markdown: String,
+    code: Vec<String>,
+}
+
+impl Expanded {
+    /// Turn callout containers into fenced blocks, which the code set aside inside them can't
+    /// close once it's put back.
+    pub fn expand_containers(&mut self) {
+        self.markdown = directives::expand_containers(&self.markdown, &self.code);
+    }
+
+    /// Set aside the page's own code spans and fenced code too.
+    ///
+    /// Answers and directives aren't code, since their content is markdown or settings that can
+    /// use templates and inline markup.
+    pub fn set_aside_code(&mut self, markdown_options: &comrak::Options) {
+        let arena = Arena::new();
+        let root = parse_document(&arena, &self.markdown, markdown_options);
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(self.markdown.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+
+        let mut ranges = vec![];
+        for node in root.descendants() {
+            let data = node.data.borrow();
+            let is_code = match &data.value {
+                NodeValue::Code(_) => true,
+                NodeValue::CodeBlock(block) => {
+                    let name = block.info.split_whitespace().next().unwrap_or_default();
+                    name != "answer" && !directives::is_directive(name)
+                }
+                _ => false,
+            };
+            if is_code && let Some(range) = byte_range(&line_starts, data.sourcepos) {
+                ranges.push(range);
+            }
+        }
+
+        let mut markdown = String::with_capacity(self.markdown.len());
+        let mut last = 0;
+        for range in ranges {
+            let Some(code) = self
+                .markdown
+                .get(range.clone())
+                .filter(|_| range.start >= last)
+            else {
+                continue;
+            };
+            markdown.push_str(&self.markdown[last..range.start]);
+            self.code.push(code.to_string());
+            markdown.push_str(&code_placeholder(self.code.len() - 1));
+            last = range.end;
+        }
+        markdown.push_str(&self.markdown[last..]);
+        self.markdown = markdown;
+    }
+
+    /// Put the included code back into `markdown`, once nothing else will rewrite it.
+    ///
+    /// Code often looks like template syntax or inline markup, and has to come out as written.
+    pub fn restore_code(&self, markdown: &str) -> String {
+        let mut markdown = markdown.to_string();
+        for (i, code) in self.code.iter().enumerate() {
+            markdown = markdown.replace(&code_placeholder(i), code);
+        }
+        markdown
+    }
+}
+
+fn code_placeholder(index: usize) -> String {
+    format!("{CODE_PLACEHOLDER}{index}{CODE_PLACEHOLDER}")
+}
+
+// The bytes a node was parsed from, given the offset each line starts at
+fn byte_range(line_starts: &[usize], sourcepos: Sourcepos) -> Option<Range<usize>> {
+    let start = line_starts.get(sourcepos.start.line.checked_sub(1)?)?;
+    let end = line_starts.get(sourcepos.end.line.checked_sub(1)?)?;
+    Some(start + sourcepos.start.column.checked_sub(1)?..end + sourcepos.end.column)
+}
+
+/// Expand markup that pulls in other files: `{{ include "file.md" }}` (recursively),
+/// `{{ table "data.csv" }}` (or a spreadsheet), and `{{ code "program.py" }}`.
+///
+/// Paths are relative to the file containing the markup.
+pub fn expand_includes(markdown: &str, page_path: &Path) -> Result<Expanded> {
+    let mut code = vec![];
+    let markdown = expand(markdown, page_path, &mut vec![], &mut code)?;
+    Ok(Expanded { markdown, code })
+}
+
+fn expand(
+    markdown: &str,
+    path: &Path,
+    stack: &mut Vec<PathBuf>,
+    code_blocks: &mut Vec<String>,
+) -> Result<String> {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if stack.contains(&canonical) {
+        return Err(anyhow!("{} includes itself", path.display()));
+    }
+    stack.push(canonical);
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut result = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+    while let Some(start) = rest.find("{{") {
+        let after_open = &rest[start + 2..];
+        let Some(end) = after_open.find("}}") else {
+            break;
+        };
+        result.push_str(&rest[..start]);
+
+        match parse_file_markup(&after_open[..end]) {
+            Some(FileMarkup {
+                name: "include",
+                path: included,
+                ..
+            }) => {
+                let included = dir.join(included);
+                let content = fs::read_to_string(&included).context(format!(
+                    "Failed to read included file {} (from {})",
+                    included.display(),
+                    path.display()
+                ))?;
+                result.push_str(&expand(&content, &included, stack, code_blocks)?);
+            }
+            Some(FileMarkup {
+                name: "table",
+                path: table,
+                args,
+            }) => {
+                let table = dir.join(table);
+                result.push_str(
+                    &table::render(&table, &args)
+                        .context(format!("Invalid table {}", table.display()))?,
+                );
+            }
+            Some(FileMarkup {
+                name: "code",
+                path: code,
+                args,
+            }) => {
+                let code = dir.join(code);
+                code_blocks.push(
+                    code::render(&code, &args)
+                        .context(format!("Invalid code include {}", code.display()))?,
+                );
+                result.push_str(&code_placeholder(code_blocks.len() - 1));
+            }
+            _ => result.push_str(&rest[start..start + end + 4]),
+        }
+        rest = &after_open[end + 2..];
+    }
+    result.push_str(rest);
+
+    stack.pop();
+    Ok(result)
+}
+
+/// Files named by file markup that don't exist, following the includes that do.
+pub fn missing_files(markdown: &str, page_path: &Path) -> Vec<PathBuf> {
+    included_files(markdown, page_path)
+        .into_iter()
+        .filter(|file| !file.is_file())
+        .collect()
+}
+
+/// Every file named by file markup, following the includes that exist.
+pub fn included_files(markdown: &str, page_path: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    find_files(markdown, page_path, &mut vec![], &mut files);
+    files
+}
+
+fn find_files(markdown: &str, path: &Path, stack: &mut Vec<PathBuf>, files: &mut Vec<PathBuf>) {
+    // Cycles are reported when the page is rendered
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if stack.contains(&canonical) {
+        return;
+    }
+    stack.push(canonical);
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut rest = markdown;
+    while let Some(start) = rest.find("{{") {
+        let after_open = &rest[start + 2..];
+        let Some(end) = after_open.find("}}") else {
+            break;
+        };
+        if let Some(markup) = parse_file_markup(&after_open[..end])
+            && matches!(markup.name, "include" | "table" | "code")
+        {
+            let file = dir.join(markup.path);
+            if markup.name == "include"
+                && let Ok(content) = fs::read_to_string(&file)
+            {
+                find_files(&content, &file, stack, files);
+            }
+            files.push(file);
+        }
+        rest = &after_open[end + 2..];
+    }
+
+    stack.pop();
+}
+
+// Returns `None` for anything that isn't file markup, like template expressions
+fn parse_file_markup(inner: &str) -> Option<FileMarkup<'_>> {
+    let inner = inner.trim();
+    let (name, rest) = inner.split_once(char::is_whitespace)?;
+    let rest = rest.trim_start().strip_prefix('"')?;
+    let (path, rest) = rest.split_once('"')?;
+    let args = parse_args(rest)?;
+
+    Some(FileMarkup { name, path, args })
+}
+
+// `key=value` pairs, where values can be quoted to include spaces
+fn parse_args(text: &str) -> Option<Vec<(&str, &str)>> {
+    let mut args = vec![];
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let (key, after) = rest.split_once('=')?;
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return None;
+        }
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"')?,
+            None => after.split_once(char::is_whitespace).unwrap_or((after, "")),
+        };
+        args.push((key, value));
+        rest = after.trim_start();
+    }
+    Some(args)
+}
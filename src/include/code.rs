@@ -0,0 +1,79 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+
+/// Render a source file as a highlighted code block with line numbers.
+///
+/// Options are `lines` (a 1-based inclusive range like `1..30`, `12..`, or `..8`), `lang`
+/// (defaulting to the file's extension), and `numbers` (`true` by default).
+pub fn render(path: &Path, args: &[(&str, &str)]) -> Result<String> {
+    let mut lines = None;
+    let mut lang = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut numbers = true;
+    for &(key, value) in args {
+        match key {
+            "lines" => lines = Some(value),
+            "lang" => lang = value.to_string(),
+            "numbers" => {
+                numbers = value
+                    .parse()
+                    .map_err(|_| anyhow!("numbers must be true or false, got {value:?}"))?
+            }
+            _ => return Err(anyhow!("Unknown code option {key:?}")),
+        }
+    }
+
+    let source = fs::read_to_string(path).context(format!("Failed to read {}", path.display()))?;
+    let all_lines: Vec<&str> = source.lines().collect();
+    let (first, last) = match lines {
+        Some(lines) => line_range(lines, all_lines.len())?,
+        None => (1, all_lines.len()),
+    };
+    let mut code = all_lines[first.saturating_sub(1)..last].join("\n");
+    code.push('\n');
+
+    // The fence has to be longer than any backtick run in the code
+    let longest_run = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat((longest_run + 1).max(3));
+    let block = format!("{fence}{lang}\n{code}{fence}\n");
+    if !numbers {
+        return Ok(block);
+    }
+    let gutter: Vec<String> = (first..=last).map(|n| n.to_string()).collect();
+    Ok(format!(
+        "<div class=\"code-listing\">\n<pre class=\"line-numbers\" aria-hidden=\"true\">{}</pre>\n\n{block}\n</div>\n",
+        gutter.join("\n")
+    ))
+}
+
+// The first and last line numbers of a range like `3..10`
+fn line_range(lines: &str, count: usize) -> Result<(usize, usize)> {
+    let invalid = || anyhow!("Invalid line range {lines:?} (expected something like 1..30)");
+    let parse = |n: &str, default| -> Result<usize> {
+        if n.is_empty() {
+            Ok(default)
+        } else {
+            n.trim().parse().map_err(|_| invalid())
+        }
+    };
+    let (first, last) = match lines.split_once("..") {
+        Some((first, last)) => (parse(first, 1)?, parse(last, count)?),
+        None => {
+            let line = parse(lines, 0)?;
+            (line, line)
+        }
+    };
+    if first == 0 || first > last {
+        return Err(invalid());
+    }
+    if last > count {
+        return Err(anyhow!(
+            "Line {last} is past the end of the file ({count} lines)"
+        ));
+    }
+    Ok((first, last))
+}
@@ -0,0 +1,167 @@
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use calamine::{Reader, open_workbook_auto};
+
+use crate::html_gen::{escape_attribute, escape_html};
+
+const SPREADSHEET_EXTENSIONS: &[&str] = &["xlsx", "xlsm", "xlsb", "xls", "ods"];
+
+/// Render a CSV file or spreadsheet as an HTML table.
+///
+/// Options are `columns` (a comma separated list of header names or 1-based column numbers),
+/// `header` (whether the first row is a header, `true` by default), and `class`. Spreadsheets
+/// also take `sheet` (a sheet name, defaulting to the first) and `range` (like `A1:C10`).
+pub fn render(path: &Path, args: &[(&str, &str)]) -> Result<String> {
+    let mut columns = None;
+    let mut has_header = true;
+    let mut class = None;
+    let mut sheet = None;
+    let mut range = None;
+    for &(key, value) in args {
+        match key {
+            "columns" => columns = Some(value),
+            "header" => {
+                has_header = value
+                    .parse()
+                    .map_err(|_| anyhow!("header must be true or false, got {value:?}"))?
+            }
+            "class" => class = Some(value),
+            "sheet" => sheet = Some(value),
+            "range" => range = Some(value),
+            _ => return Err(anyhow!("Unknown table option {key:?}")),
+        }
+    }
+
+    let is_spreadsheet = path
+        .extension()
+        .is_some_and(|ext| SPREADSHEET_EXTENSIONS.contains(&&*ext.to_string_lossy()));
+    let mut rows = if is_spreadsheet {
+        read_spreadsheet(path, sheet, range)?
+    } else if sheet.is_some() || range.is_some() {
+        return Err(anyhow!("sheet and range only work with spreadsheets"));
+    } else {
+        read_csv(path)?
+    };
+    let header = if has_header && !rows.is_empty() {
+        Some(rows.remove(0))
+    } else {
+        None
+    };
+
+    let indexes: Option<Vec<usize>> = columns
+        .map(|columns| {
+            columns
+                .split(',')
+                .map(|column| column_index(column.trim(), header.as_deref()))
+                .collect()
+        })
+        .transpose()?;
+    let select = |row: &[String]| -> Vec<String> {
+        match &indexes {
+            Some(indexes) => indexes
+                .iter()
+                .map(|&i| row.get(i).cloned().unwrap_or_default())
+                .collect(),
+            None => row.to_vec(),
+        }
+    };
+
+    let class = match class {
+        Some(class) => format!("csv-table {}", escape_attribute(class)),
+        None => "csv-table".to_string(),
+    };
+    let mut html = format!("<table class=\"{class}\">\n");
+    if let Some(header) = &header {
+        html.push_str("<thead><tr>");
+        for cell in select(header) {
+            html.push_str(&format!("<th>{}</th>", escape_html(&cell)));
+        }
+        html.push_str("</tr></thead>\n");
+    }
+    html.push_str("<tbody>\n");
+    for row in &rows {
+        html.push_str("<tr>");
+        for cell in select(row) {
+            html.push_str(&format!("<td>{}</td>", escape_html(&cell)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</tbody>\n</table>\n");
+
+    Ok(html)
+}
+
+fn read_csv(path: &Path) -> Result<Vec<Vec<String>>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)
+        .context(format!("Failed to read {}", path.display()))?;
+    reader
+        .records()
+        .map(|record| Ok(record?.iter().map(String::from).collect()))
+        .collect::<Result<_>>()
+        .context(format!("Failed to parse {}", path.display()))
+}
+
+fn read_spreadsheet(
+    path: &Path,
+    sheet: Option<&str>,
+    range: Option<&str>,
+) -> Result<Vec<Vec<String>>> {
+    let mut workbook =
+        open_workbook_auto(path).context(format!("Failed to read {}", path.display()))?;
+    let sheet = match sheet {
+        Some(sheet) => sheet.to_string(),
+        None => workbook
+            .sheet_names()
+            .first()
+            .cloned()
+            .ok_or_else(|| anyhow!("{} has no sheets", path.display()))?,
+    };
+    let mut cells = workbook
+        .worksheet_range(&sheet)
+        .context(format!("Failed to read sheet {sheet:?}"))?;
+    if let Some(range) = range {
+        let (start, end) = range
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Expected a range like A1:C10, got {range:?}"))?;
+        cells = cells.range(cell_position(start)?, cell_position(end)?);
+    }
+
+    Ok(cells
+        .rows()
+        .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+        .collect())
+}
+
+// The zero-based (row, column) of a cell reference like `B3`
+fn cell_position(cell: &str) -> Result<(u32, u32)> {
+    let invalid = || anyhow!("Invalid cell {cell:?}");
+    let cell = cell.trim().to_ascii_uppercase();
+    let split = cell
+        .find(|c: char| c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (letters, digits) = cell.split_at(split);
+    if letters.is_empty() || !letters.chars().all(|c| c.is_ascii_uppercase()) {
+        return Err(invalid());
+    }
+    let column = letters.bytes().fold(0, |column, letter| {
+        column * 26 + u32::from(letter - b'A') + 1
+    });
+    let row: u32 = digits.parse().map_err(|_| invalid())?;
+    Ok((row.checked_sub(1).ok_or_else(invalid)?, column - 1))
+}
+
+// A column given by its header name or 1-based number
+fn column_index(column: &str, header: Option<&[String]>) -> Result<usize> {
+    if let Ok(number) = column.parse::<usize>() {
+        return number
+            .checked_sub(1)
+            .ok_or_else(|| anyhow!("Column numbers start at 1"));
+    }
+    header
+        .and_then(|header| header.iter().position(|name| name.trim() == column))
+        .ok_or_else(|| anyhow!("No column named {column:?}"))
+}
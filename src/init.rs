@@ -0,0 +1,48 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::config::InitTemplate;
+
+const CONFIG: &str = include_str!("init/worksheet.yaml");
+const STYLESHEET: &str = include_str!("init/style.css");
+
+/// Create a starter config, page, and stylesheet in `dir`.
+///
+/// Existing files are only overwritten with `force`.
+pub fn init(dir: &Path, template: InitTemplate, force: bool) -> Result<()> {
+    let page = match template {
+        InitTemplate::Basic => include_str!("init/basic.md"),
+        InitTemplate::Math => include_str!("init/math.md"),
+        InitTemplate::Quiz => include_str!("init/quiz.md"),
+    };
+    let files = [
+        ("worksheet.yaml", CONFIG),
+        ("worksheet.md", page),
+        ("style.css", STYLESHEET),
+    ];
+
+    if !force {
+        let existing: Vec<&str> = files
+            .iter()
+            .map(|&(name, _)| name)
+            .filter(|name| dir.join(name).exists())
+            .collect();
+        if !existing.is_empty() {
+            return Err(anyhow!(
+                "{} already exists (pass --force to overwrite)",
+                existing.join(", ")
+            ));
+        }
+    }
+
+    for (name, contents) in files {
+        let path = dir.join(name);
+        fs::write(&path, contents).context(format!("Failed to write {}", path.display()))?;
+        println!("✓ Created {}", path.display());
+    }
+    println!("Run `worksheet-generator` to build worksheet.pdf");
+
+    Ok(())
+}
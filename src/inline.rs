@@ -0,0 +1,66 @@
+use anyhow::Result;
+
+use crate::context::RenderContext;
+use crate::directives;
+
+/// Expand inline `{{name: ...}}` markup in raw markdown before it's handed to comrak.
+///
+/// Markup that isn't recognized is left untouched.
+pub fn expand_inline_markup(markdown: &str, ctx: &mut RenderContext) -> Result<String> {
+    let mut result = String::with_capacity(markdown.len());
+    let mut rest = markdown;
+
+    while let Some(start) = rest.find("{{") {
+        let after_open = &rest[start + 2..];
+        let Some(end) = after_open.find("}}") else {
+            break;
+        };
+        let inner = after_open[..end].trim();
+        result.push_str(&rest[..start]);
+
+        match render_markup(inner, ctx)? {
+            Some(html) => result.push_str(&html),
+            None => result.push_str(&rest[start..start + end + 4]),
+        }
+        rest = &after_open[end + 2..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+fn render_markup(inner: &str, ctx: &mut RenderContext) -> Result<Option<String>> {
+    let Some((name, value)) = inner.split_once(':') else {
+        return Ok(None);
+    };
+
+    let html = match name.trim() {
+        "answer" => render_inline_answer(value.trim(), ctx.show_answers),
+        "cloze" => {
+            let word = value.trim();
+            ctx.cloze_words.push(word.to_string());
+            render_cloze(word, ctx.show_answers)
+        }
+        name => return directives::render_inline(name, value.trim(), ctx),
+    };
+
+    Ok(Some(html))
+}
+
+// Like an inline answer, but the blank is sized to fit the word
+fn render_cloze(word: &str, show_answers: bool) -> String {
+    if show_answers {
+        format!(r#"<span class="answer cloze">{word}</span>"#)
+    } else {
+        let width = word.chars().count() as f64 * 0.6 + 1.5;
+        format!(r#"<span class="answer-blank cloze" style="min-width: {width:.1}em"></span>"#)
+    }
+}
+
+pub fn render_inline_answer(answer: &str, show_answers: bool) -> String {
+    if show_answers {
+        format!(r#"<span class="answer">{answer}</span>"#)
+    } else {
+        r#"<span class="answer-blank"></span>"#.to_string()
+    }
+}
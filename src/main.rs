@@ -1,61 +1,562 @@
+mod assets;
+mod bubble_sheet;
+mod check;
 mod config;
+mod context;
+mod directives;
+mod docx_gen;
+mod export;
+mod flashcards;
+mod fonts;
+mod front_matter;
 mod html_gen;
+mod include;
+mod init;
+mod inline;
+mod manifest;
+mod math;
+mod new;
+mod page;
+mod pdf_gen;
+mod pdf_post;
+mod png_gen;
+mod progress;
+mod question_bank;
+mod roster;
+mod serve;
+mod site;
+mod template;
+mod title_page;
+mod typst_gen;
+mod warnings;
+mod watch;
 
-use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
 use clap::Parser;
+use rayon::prelude::*;
 
-use config::{Args, Options, OutputFormat};
+use config::{Args, Cli, Command, Config, Options, OutputFormat};
+use context::RenderLog;
+use manifest::Manifest;
+use roster::Roster;
+use warnings::Warnings;
 
 fn main() -> Result<()> {
-    let args = Args::parse();
-    let config = Options::load_config(args.config.as_deref())?;
+    let cli = Cli::parse();
+    match cli.command.unwrap_or(Command::Build(cli.args)) {
+        Command::Build(args) => {
+            let args = with_discovered_config(args)?;
+            if let OutputFormat::Site = args.output_format {
+                return site::build_site(args);
+            }
+            build(args)
+        }
+        Command::Watch(args) => watch::watch(with_discovered_config(args)?),
+        Command::Serve { address, args } => serve::serve(with_discovered_config(args)?, &address),
+        Command::Check {
+            spelling,
+            dictionary,
+            allowlist,
+            args,
+        } => {
+            let spelling = spelling.then_some(check::SpellingOptions {
+                dictionary,
+                allowlist,
+            });
+            check::check(with_discovered_config(args)?, spelling)
+        }
+        Command::Flashcards {
+            cards,
+            columns,
+            rows,
+            args,
+        } => build_flashcards(with_discovered_config(args)?, &cards, columns, rows),
+        Command::Init { template, force } => init::init(Path::new("."), template, force),
+        Command::New {
+            template,
+            path,
+            title,
+            date,
+            force,
+        } => {
+            let path = path.unwrap_or_else(|| format!("{}.md", template.name()).into());
+            new::new_page(template, &path, title.as_deref(), date.as_deref(), force)
+        }
+    }
+}
+
+fn with_discovered_config(mut args: Args) -> Result<Args> {
+    // A site is built from a directory of configs, not a single one
+    if args.config.is_none() && !args.no_config && !matches!(args.output_format, OutputFormat::Site)
+    {
+        args.config = config::discover_config()?;
+    }
+    Ok(args)
+}
+
+fn build(args: Args) -> Result<()> {
+    progress::set_stderr(false);
+    let configs = Options::load_configs(
+        args.config.as_deref(),
+        args.profile.as_deref(),
+        args.target.as_deref(),
+    )?;
+    if configs.len() > 1 && args.output_file.is_some() {
+        // Every target would be written to the same file
+        return Err(anyhow!(
+            "Pick a --target to build when setting the output file"
+        ));
+    }
+    let mut manifest = args.manifest.as_ref().map(|_| Manifest::default());
+    if let (Some(manifest), Some(config_path)) = (&mut manifest, &args.config) {
+        manifest.add_config(config_path);
+    }
+    // Collected from every target, for the manifest
+    let warnings = Warnings::default();
+    for config in configs {
+        build_config(args.clone(), config, &warnings, manifest.as_mut())?;
+    }
+    if let (Some(manifest), Some(path)) = (manifest, &args.manifest) {
+        if args.dry_run {
+            report_dry_run(path, "manifest");
+        } else {
+            manifest.write(path, &warnings)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn build_config(
+    args: Args,
+    config: Config,
+    warnings: &Warnings,
+    manifest: Option<&mut Manifest>,
+) -> Result<()> {
+    let open = args.open && !args.dry_run;
+    let mut options = Options::from_args_and_config(args, config)?;
+    options.warnings = options.warnings.sharing(warnings);
+    if options.dry_run {
+        // Assets would otherwise be copied next to the HTML while it's rendered
+        options.assets_dir = None;
+    }
+
+    // A dry run doesn't convert anything, so it doesn't need the tools
+    match options.output_format {
+        _ if options.dry_run => {}
+        OutputFormat::Pdf => {
+            pdf_gen::backend(&options).check_available()?;
+            pdf_post::check_available(&options)?;
+        }
+        OutputFormat::Png => {
+            pdf_gen::backend(&options).check_available()?;
+            png_gen::check_available()?;
+        }
+        _ => {}
+    }
+
+    let page_options = options.page_options()?;
+    if let Some(out_dir) = &options.out_dir
+        && !options.dry_run
+    {
+        std::fs::create_dir_all(out_dir)
+            .context(format!("Failed to create directory {}", out_dir.display()))?;
+    }
+    let documents = page_options
+        .iter()
+        .flat_map(Options::variant_options)
+        .map(|mut options| {
+            // Each document's renders are logged separately, for its manifest entry
+            options.render_log = RenderLog::default();
+            let jobs = copies(&options)?;
+            Ok((options, jobs))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let jobs: Vec<&Job> = documents.iter().flat_map(|(_, jobs)| jobs).collect();
+    let total = jobs.len();
+    // Every status line after this would end up in the document
+    if !options.dry_run && jobs.iter().any(|job| config::is_stdout(&job.output_file)) {
+        progress::set_stderr(true);
+    }
+
+    // Files are independent, so they're built in parallel. With several, one failing doesn't
+    // stop the rest.
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.jobs.map_or(0, usize::from))
+        .build()
+        .context("Failed to start build threads")?;
+    progress::start(total);
+    let results: Vec<Result<()>> = pool.install(|| {
+        jobs.par_iter()
+            .map(|job| {
+                progress::set_message(job.output_file.display().to_string());
+                let result = job.run();
+                if total > 1
+                    && let Err(e) = &result
+                {
+                    progress::suspend(|| {
+                        eprintln!("✗ Failed to build {}: {e:#}", job.output_file.display())
+                    });
+                }
+                progress::inc();
+                result
+            })
+            .collect()
+    });
+    progress::finish();
+
+    let mut results = results.into_iter();
+    let mut failures = 0;
+    let mut built = vec![];
+    for (options, jobs) in &documents {
+        let mut outputs = vec![];
+        for (job, result) in jobs.iter().zip(&mut results) {
+            match result {
+                Ok(()) => outputs.push(job.output_file.clone()),
+                Err(e) if total == 1 => return Err(e),
+                Err(_) => failures += 1,
+            }
+        }
+        built.push((options, outputs));
+    }
+    let randomized = documents
+        .iter()
+        .any(|(options, _)| options.render_log.rendered().randomized);
+    if options.random_seed && randomized {
+        let seed = options.seed;
+        progress::println(&format!(
+            "Using random seed {seed} (pass --seed {seed} to reproduce)"
+        ));
+    }
+    if let Some(manifest) = manifest {
+        for (options, outputs) in built {
+            manifest.add_document(options, outputs);
+        }
+    }
+    if failures > 0 {
+        return Err(anyhow!("{failures} of {total} files failed to build"));
+    }
+    if total > 1 && !options.dry_run {
+        progress::println(&format!("✓ Built {total} files"));
+    }
+    if let Some(out_dir) = options.out_dir.as_ref().filter(|_| !options.dry_run) {
+        progress::println(&format!(
+            "✓ {} pages rendered into {}:",
+            page_options.len(),
+            out_dir.display()
+        ));
+        for page_options in &page_options {
+            progress::println(&format!(
+                "  {} → {}",
+                page_options.pages[0].display(),
+                page_options.output_file.display()
+            ));
+        }
+    }
+
+    if open {
+        // The first variant, if there are several
+        let options = &options.variant_options()[0];
+        let output = if let Some(out_dir) = &options.out_dir {
+            out_dir.clone()
+        } else if options.split_pages {
+            options.output_file.with_extension("").join("index.html")
+        } else {
+            options.output_file.clone()
+        };
+        if config::is_stdout(&output) {
+            eprintln!("Warning: Output written to stdout can't be opened");
+        } else if !output.exists() {
+            // Roster copies and PNG pages are named after the output file
+            eprintln!(
+                "Warning: {} wasn't generated, so it can't be opened",
+                output.display()
+            );
+        } else {
+            open_output(&output)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn open_output(path: &Path) -> Result<()> {
+    open::that(path).context(format!("Failed to open {}", path.display()))
+}
+
+fn build_flashcards(mut args: Args, cards: &Path, columns: usize, rows: usize) -> Result<()> {
+    // The card list stands in for the pages, so the default output name comes from it
+    args.pages = vec![cards.to_path_buf()];
+    let config = Options::load_config(
+        args.config.as_deref(),
+        args.profile.as_deref(),
+        args.target.as_deref(),
+    )?;
     let options = Options::from_args_and_config(args, config)?;
+    let markdown = options.markdown.comrak_options();
+    let html = flashcards::generate_html(cards, columns, rows, &markdown)?;
+    if options.dry_run {
+        report_dry_run(&options.output_file, "flashcards");
+        return Ok(());
+    }
+    write_html_document(&options, &html, &options.output_file)
+}
+
+/// One output file to build.
+struct Job {
+    options: Options,
+    output_file: PathBuf,
+    kind: JobKind,
+}
 
+enum JobKind {
+    BubbleSheet,
+    // The bubble sheet with the correct answers filled in
+    BubbleSheetKey,
+    Worksheet,
+    AnswerKey,
+}
+
+impl Job {
+    fn new(options: &Options, output_file: &Path, kind: JobKind) -> Self {
+        Job {
+            options: options.clone(),
+            output_file: output_file.to_path_buf(),
+            kind,
+        }
+    }
+
+    fn run(&self) -> Result<()> {
+        match self.kind {
+            JobKind::BubbleSheet | JobKind::BubbleSheetKey => {
+                let show_answers = matches!(self.kind, JobKind::BubbleSheetKey);
+                let html = bubble_sheet::generate_bubble_sheet(&self.options, show_answers)?;
+                if self.options.dry_run {
+                    let description = if show_answers {
+                        "bubble sheet, answer key"
+                    } else {
+                        "bubble sheet"
+                    };
+                    report_dry_run(&self.output_file, description);
+                    Ok(())
+                } else {
+                    write_html_document(&self.options, &html, &self.output_file)
+                }
+            }
+            JobKind::Worksheet => write_document(&self.options, &self.output_file, false),
+            JobKind::AnswerKey => write_document(&self.options, &self.output_file, true),
+        }
+    }
+}
+
+// The worksheet and answer key, personalized for each student if there's a roster
+fn copies(options: &Options) -> Result<Vec<Job>> {
+    let mut jobs = vec![];
+    // Students all fill in the same sheet, so it isn't personalized
+    if let Some(bubble_sheet_file) = &options.bubble_sheet_file {
+        jobs.push(Job::new(options, bubble_sheet_file, JobKind::BubbleSheet));
+        if options.answer_key_file.is_some() {
+            let key_file = config::with_stem_suffix(bubble_sheet_file, "key");
+            jobs.push(Job::new(options, &key_file, JobKind::BubbleSheetKey));
+        }
+    }
+
+    let Some(roster_path) = &options.roster else {
+        jobs.push(Job::new(options, &options.output_file, JobKind::Worksheet));
+        if let Some(answer_key_file) = &options.answer_key_file {
+            jobs.push(Job::new(options, answer_key_file, JobKind::AnswerKey));
+        }
+        return Ok(jobs);
+    };
+
+    let roster = Roster::load(roster_path)?;
+    for index in 0..roster.students.len() {
+        let student_options = roster.student_options(options, index)?;
+        jobs.push(Job::new(
+            &student_options,
+            &student_options.output_file,
+            JobKind::Worksheet,
+        ));
+    }
+    // One shared answer key, with the student fields left blank
+    if let Some(answer_key_file) = &options.answer_key_file {
+        let mut key_options = options.clone();
+        key_options.variables.insert(
+            "student".to_string(),
+            serde_yaml::to_value(roster.blank_student())?,
+        );
+        jobs.push(Job::new(&key_options, answer_key_file, JobKind::AnswerKey));
+    }
+
+    Ok(jobs)
+}
+
+fn write_document(options: &Options, output_file: &Path, show_answers: bool) -> Result<()> {
+    if options.dry_run {
+        return render_document(options, output_file, show_answers);
+    }
     match options.output_format {
-        OutputFormat::Html => {
-            let html = html_gen::generate_html(&options.pages, options.stylesheet.as_deref())?;
-            std::fs::write(&options.output_file, html).context(format!(
-                "Failed to write HTML to {}",
-                options.output_file.display()
+        OutputFormat::Html if options.split_pages => {
+            // One file per page, in a directory named after the output file
+            let output_dir = output_file.with_extension("");
+            std::fs::create_dir_all(&output_dir).context(format!(
+                "Failed to create directory {}",
+                output_dir.display()
             ))?;
-            println!("✓ HTML generated at {}", options.output_file.display());
+            for (path, html) in html_gen::generate_split_html(options, &output_dir, show_answers)? {
+                let html = if options.minify {
+                    html_gen::minify(&html)
+                } else {
+                    html
+                };
+                std::fs::write(&path, html)
+                    .context(format!("Failed to write HTML to {}", path.display()))?;
+            }
+            progress::println(&format!("✓ HTML generated in {}", output_dir.display()));
         }
-        OutputFormat::Pdf => {
-            which::which("weasyprint").context("'weasyprint' not found in PATH".to_string())?;
-            let html = html_gen::generate_html(&options.pages, options.stylesheet.as_deref())?;
-            pdf_gen::generate_pdf(&html, &options.output_file)?;
-            println!("✓ PDF generated at {}", options.output_file.display());
+        OutputFormat::Html | OutputFormat::Pdf | OutputFormat::Png => {
+            let html = html_gen::generate_html(options, show_answers)?;
+            write_html_document(options, &html, output_file)?;
+        }
+        OutputFormat::Typst => {
+            let typst = typst_gen::generate_typst(options, show_answers)?;
+            write_output(output_file, |path| {
+                std::fs::write(path, &typst)
+                    .context(format!("Failed to write Typst to {}", path.display()))
+            })?;
+            report_generated("Typst", output_file);
+        }
+        OutputFormat::Docx => {
+            write_output(output_file, |path| {
+                docx_gen::generate_docx(options, show_answers, path)
+            })?;
+            report_generated("DOCX", output_file);
+        }
+        OutputFormat::Site => {
+            return Err(anyhow!("Sites are built from a directory of configs"));
         }
     }
 
     Ok(())
 }
 
-mod pdf_gen {
-    use std::io::Write;
-    use std::path::Path;
-    use std::process::{Command, Stdio};
-
-    use anyhow::{Context, Result, anyhow};
+// Render a document for a dry run, and report what would have been written
+fn render_document(options: &Options, output_file: &Path, show_answers: bool) -> Result<()> {
+    let mut description = match options.pages.len() {
+        1 => "1 page".to_string(),
+        n => format!("{n} pages"),
+    };
+    if let Some(variant) = &options.variant {
+        description.push_str(&format!(", form {variant}"));
+    }
+    if show_answers {
+        description.push_str(", answer key");
+    }
 
-    pub fn generate_pdf(html: &str, output: &Path) -> Result<()> {
-        // Pipe HTML directly to weasyprint via stdin
-        let mut weasyprint_cmd = Command::new("weasyprint");
-        let mut weasyprint = weasyprint_cmd
-            .arg("-") // Read from stdin
-            .arg(output)
-            .stdin(Stdio::piped())
-            .spawn()
-            .context("Failed to spawn weasyprint")?;
-        if let Some(mut stdin) = weasyprint.stdin.take() {
-            stdin.write_all(html.as_bytes())?;
+    match options.output_format {
+        OutputFormat::Html if options.split_pages => {
+            let output_dir = output_file.with_extension("");
+            for (path, _) in html_gen::generate_split_html(options, &output_dir, show_answers)? {
+                report_dry_run(&path, &description);
+            }
+            return Ok(());
+        }
+        OutputFormat::Html | OutputFormat::Pdf | OutputFormat::Png => {
+            html_gen::generate_html(options, show_answers)?;
+        }
+        OutputFormat::Typst => {
+            typst_gen::generate_typst(options, show_answers)?;
         }
+        OutputFormat::Docx => {
+            docx_gen::render_docx(options, show_answers)?;
+        }
+        OutputFormat::Site => {
+            return Err(anyhow!("Sites are built from a directory of configs"));
+        }
+    }
+    report_dry_run(output_file, &description);
 
-        let status = weasyprint.wait().context("Weasyprint failed")?;
+    Ok(())
+}
+
+fn report_dry_run(output_file: &Path, description: &str) {
+    if config::is_stdout(output_file) {
+        progress::println(&format!("Would write {description} to stdout"));
+    } else {
+        progress::println(&format!(
+            "Would write {} ({description})",
+            output_file.display()
+        ));
+    }
+}
 
-        if !status.success() {
-            return Err(anyhow!("weasyprint failed"));
+// Write already rendered HTML in the output format
+fn write_html_document(options: &Options, html: &str, output_file: &Path) -> Result<()> {
+    let minified;
+    let html = if options.minify {
+        minified = html_gen::minify(html);
+        &minified
+    } else {
+        html
+    };
+    match options.output_format {
+        OutputFormat::Html => {
+            write_output(output_file, |path| {
+                std::fs::write(path, html)
+                    .context(format!("Failed to write HTML to {}", path.display()))
+            })?;
+            report_generated("HTML", output_file);
+        }
+        OutputFormat::Pdf => {
+            write_output(output_file, |path| {
+                pdf_gen::backend(options).generate_pdf(html, path)?;
+                pdf_post::postprocess(options, path)
+            })?;
+            report_generated("PDF", output_file);
+        }
+        OutputFormat::Png => {
+            let backend = pdf_gen::backend(options);
+            let images = png_gen::generate_png(backend.as_ref(), html, output_file)?;
+            for image in images {
+                progress::println(&format!("✓ PNG generated at {}", image.display()));
+            }
         }
-        Ok(())
+        OutputFormat::Typst | OutputFormat::Docx | OutputFormat::Site => {
+            return Err(anyhow!(
+                "{} can only be written as HTML, PDF, or PNG",
+                output_file.display()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Backends write files, so output for stdout goes through a temporary file first
+fn write_output(output_file: &Path, write: impl FnOnce(&Path) -> Result<()>) -> Result<()> {
+    if !config::is_stdout(output_file) {
+        return write(output_file);
+    }
+    let temp_file =
+        std::env::temp_dir().join(format!("worksheet-generator-{}-output", std::process::id()));
+    let result = write(&temp_file).and_then(|()| {
+        let bytes =
+            std::fs::read(&temp_file).context(format!("Failed to read {}", temp_file.display()))?;
+        std::io::stdout()
+            .write_all(&bytes)
+            .context("Failed to write to stdout")
+    });
+    let _ = std::fs::remove_file(&temp_file);
+    result
+}
+
+fn report_generated(kind: &str, output_file: &Path) {
+    if config::is_stdout(output_file) {
+        progress::println(&format!("✓ {kind} written to stdout"));
+    } else {
+        progress::println(&format!("✓ {kind} generated at {}", output_file.display()));
     }
 }
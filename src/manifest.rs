@@ -0,0 +1,108 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::config::{self, Options};
+use crate::front_matter::is_stdin;
+use crate::progress;
+use crate::warnings::Warnings;
+
+/// A record of what a build read and wrote, for reproducing it or feeding other tools.
+#[derive(Default)]
+pub struct Manifest {
+    inputs: BTreeSet<PathBuf>,
+    documents: Vec<Document>,
+}
+
+/// One rendering of the pages, with the files written from it.
+#[derive(Serialize)]
+struct Document {
+    variant: Option<String>,
+    seed: u64,
+    /// Ids of the questions chosen from the question bank
+    questions: Vec<String>,
+    outputs: Vec<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct Input {
+    path: PathBuf,
+    // Standard input can't be read again to hash it
+    sha256: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ManifestFile<'a> {
+    version: &'static str,
+    inputs: Vec<Input>,
+    documents: &'a [Document],
+    warnings: Vec<String>,
+}
+
+impl Manifest {
+    /// Record a config file, along with any configs it `extends`.
+    pub fn add_config(&mut self, path: &Path) {
+        self.inputs.extend(config::config_files(path));
+    }
+
+    /// Record a document rendered with `options`, and the files written for it. The questions
+    /// and images come from the options' render log, so this goes after the document is built.
+    pub fn add_document(&mut self, options: &Options, outputs: Vec<PathBuf>) {
+        let rendered = options.render_log.rendered();
+        self.inputs.extend(options.input_files());
+        self.inputs.extend(rendered.images);
+        self.documents.push(Document {
+            variant: options.variant.clone(),
+            seed: options.seed,
+            questions: rendered.questions,
+            outputs,
+        });
+    }
+
+    /// Write the manifest as JSON, with a hash of each input file that could be read, and the
+    /// build's warnings.
+    pub fn write(&self, path: &Path, warnings: &Warnings) -> Result<()> {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|input| {
+                let sha256 = if is_stdin(input) {
+                    None
+                } else {
+                    match fs::read(input) {
+                        Ok(bytes) => Some(format!("{:x}", Sha256::digest(bytes))),
+                        // Missing files were already reported, or left out of the build
+                        Err(e) => {
+                            warnings.warn(
+                                format!("Failed to read {}: {e}", input.display()),
+                                "leaving out its hash",
+                            )?;
+                            None
+                        }
+                    }
+                };
+                Ok(Input {
+                    path: input.clone(),
+                    sha256,
+                })
+            })
+            .collect::<Result<_>>()?;
+        let manifest = ManifestFile {
+            version: env!("CARGO_PKG_VERSION"),
+            inputs,
+            documents: &self.documents,
+            warnings: warnings.reported(),
+        };
+
+        let json = serde_json::to_string_pretty(&manifest)?;
+        fs::write(path, json + "\n")
+            .context(format!("Failed to write manifest to {}", path.display()))?;
+        progress::println(&format!("✓ Manifest written to {}", path.display()));
+
+        Ok(())
+    }
+}
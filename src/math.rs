@@ -0,0 +1,71 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use latex2mathml::{DisplayStyle, latex_to_mathml};
+
+use crate::assets::{self, AssetMode};
+use crate::config::{MathRenderer, Options};
+
+// KaTeX's HTML needs its stylesheet (and the fonts it links to) to display properly
+const KATEX_DIST_URL: &str = "https://cdn.jsdelivr.net/npm/katex@0.16/dist";
+
+/// Render LaTeX math to HTML, so no JavaScript is needed to display it.
+pub fn render(latex: &str, display: bool, renderer: MathRenderer) -> Result<String> {
+    match renderer {
+        MathRenderer::Katex => {
+            let opts = katex::Opts::builder().display_mode(display).build()?;
+            katex::render_with_opts(latex, &opts).context(format!("Invalid math: {latex}"))
+        }
+        MathRenderer::Mathml => {
+            let style = if display {
+                DisplayStyle::Block
+            } else {
+                DisplayStyle::Inline
+            };
+            latex_to_mathml(latex, style).map_err(|e| anyhow!("Invalid math: {latex}: {e}"))
+        }
+    }
+}
+
+/// KaTeX's stylesheet, downloaded into the cache along with its fonts, and with the fonts
+/// handled like other assets so self-contained HTML doesn't depend on the CDN.
+pub fn katex_css(options: &Options) -> Result<String> {
+    let url = format!("{KATEX_DIST_URL}/katex.min.css");
+    let stylesheet = assets::fetch_cached(&url, &options.cache_dir)
+        .context("Failed to get KaTeX's stylesheet")?;
+    let css = fs::read_to_string(&stylesheet)
+        .context(format!("Failed to read {}", stylesheet.display()))?;
+    // Fonts are linked relative to the stylesheet on the CDN
+    let css = woff2_only(&css).replace("url(fonts/", &format!("url({KATEX_DIST_URL}/fonts/"));
+    let download = AssetMode::Resolve {
+        cache_dir: &options.cache_dir,
+    };
+    let css = assets::rewrite_css_urls(&css, Path::new("."), download, &options.warnings)?;
+    let mode = AssetMode::for_options(options);
+    assets::rewrite_css_urls(&css, Path::new("."), mode, &options.warnings)
+}
+
+// KaTeX lists WOFF and TrueType fallbacks for each font, which anything that can print the
+// worksheet doesn't need, and would otherwise be downloaded and embedded too
+fn woff2_only(css: &str) -> String {
+    let mut result = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("src:") {
+        let value_start = start + "src:".len();
+        let end = rest[value_start..]
+            .find(['}', ';'])
+            .map_or(rest.len(), |end| value_start + end);
+        let sources: Vec<&str> = rest[value_start..end].split(',').collect();
+        let woff2: Vec<&str> = sources
+            .iter()
+            .copied()
+            .filter(|source| source.contains(".woff2"))
+            .collect();
+        result.push_str(&rest[..value_start]);
+        result.push_str(&if woff2.is_empty() { sources } else { woff2 }.join(","));
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
@@ -0,0 +1,45 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use serde::Serialize;
+
+use crate::config::NewTemplate;
+
+#[derive(Serialize)]
+struct NewFrontMatter<'a> {
+    title: &'a str,
+    date: &'a str,
+}
+
+/// Write a new page from one of the built-in templates.
+///
+/// The title and date go in the page's front matter, so they can be changed later.
+pub fn new_page(
+    template: NewTemplate,
+    path: &Path,
+    title: Option<&str>,
+    date: Option<&str>,
+    force: bool,
+) -> Result<()> {
+    if path.exists() && !force {
+        return Err(anyhow!(
+            "{} already exists (pass --force to overwrite)",
+            path.display()
+        ));
+    }
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let front_matter = NewFrontMatter {
+        title: title.unwrap_or(template.title()),
+        date: date.unwrap_or(&today),
+    };
+    let content = format!(
+        "---\n{}---\n\n{}",
+        serde_yaml::to_string(&front_matter)?,
+        template.markdown()
+    );
+    fs::write(path, content).context(format!("Failed to write {}", path.display()))?;
+    println!("✓ Created {}", path.display());
+
+    Ok(())
+}
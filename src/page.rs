@@ -0,0 +1,38 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::config::Options;
+use crate::context::RenderContext;
+use crate::front_matter::{FrontMatter, read_page};
+use crate::include::expand_includes;
+use crate::inline::expand_inline_markup;
+use crate::template;
+
+/// A markdown page with its front matter split off and all preprocessing applied.
+pub struct Page {
+    pub front_matter: FrontMatter,
+    pub markdown: String,
+}
+
+impl Page {
+    pub fn load(page_path: &Path, options: &Options, ctx: &mut RenderContext) -> Result<Self> {
+        let (mut front_matter, markdown) = read_page(page_path)?;
+        if let Some(settings) = options.page_settings.get(page_path) {
+            settings.apply(&mut front_matter);
+        }
+        ctx.cloze_words.clear();
+        ctx.markdown = front_matter.markdown.or(&options.markdown).comrak_options();
+        let mut expanded = expand_includes(&markdown, page_path)?;
+        expanded.set_aside_code(&ctx.markdown);
+        expanded.expand_containers();
+        let markdown = expand_inline_markup(&expanded.markdown, ctx)?;
+        let markdown = template::render(&markdown, page_path, &front_matter, options, ctx)?;
+        let markdown = expanded.restore_code(&markdown);
+
+        Ok(Page {
+            front_matter,
+            markdown,
+        })
+    }
+}
@@ -0,0 +1,89 @@
+mod native;
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::config::{Options, PdfBackendKind};
+
+/// Something that can turn a complete HTML document into a PDF file.
+pub trait PdfBackend {
+    /// Fail early with a helpful message if the backend can't run.
+    fn check_available(&self) -> Result<()>;
+
+    fn generate_pdf(&self, html: &str, output: &Path) -> Result<()>;
+}
+
+pub fn backend(options: &Options) -> Box<dyn PdfBackend> {
+    let mut args = options.pdf_backend_args.clone();
+    if options.pdfa {
+        // Only weasyprint supports PDF/A, which is checked when options are resolved
+        args.splice(0..0, ["--pdf-variant".to_string(), "pdf/a-2b".to_string()]);
+    }
+    match options.pdf_backend {
+        PdfBackendKind::Weasyprint => Box::new(Weasyprint { args }),
+        PdfBackendKind::Wkhtmltopdf => Box::new(Wkhtmltopdf { args }),
+        PdfBackendKind::Native => Box::new(native::Native),
+    }
+}
+
+pub struct Weasyprint {
+    args: Vec<String>,
+}
+
+impl PdfBackend for Weasyprint {
+    fn check_available(&self) -> Result<()> {
+        check_executable("weasyprint")
+    }
+
+    fn generate_pdf(&self, html: &str, output: &Path) -> Result<()> {
+        let mut command = Command::new("weasyprint");
+        command.args(&self.args).arg("-").arg(output); // Read from stdin
+        run_with_stdin(command, "weasyprint", html)
+    }
+}
+
+pub struct Wkhtmltopdf {
+    args: Vec<String>,
+}
+
+impl PdfBackend for Wkhtmltopdf {
+    fn check_available(&self) -> Result<()> {
+        check_executable("wkhtmltopdf")
+    }
+
+    fn generate_pdf(&self, html: &str, output: &Path) -> Result<()> {
+        let mut command = Command::new("wkhtmltopdf");
+        command
+            .arg("--quiet")
+            .args(&self.args)
+            .arg("-") // Read from stdin
+            .arg(output);
+        run_with_stdin(command, "wkhtmltopdf", html)
+    }
+}
+
+pub fn check_executable(name: &str) -> Result<()> {
+    which::which(name).context(format!("'{name}' not found in PATH"))?;
+    Ok(())
+}
+
+fn run_with_stdin(mut command: Command, name: &str, input: &str) -> Result<()> {
+    // Pipe HTML directly to the backend via stdin
+    let mut child = command
+        .stdin(Stdio::piped())
+        .spawn()
+        .context(format!("Failed to spawn {name}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(input.as_bytes())?;
+    }
+
+    let status = child.wait().context(format!("{name} failed"))?;
+
+    if !status.success() {
+        return Err(anyhow!("{name} failed"));
+    }
+    Ok(())
+}
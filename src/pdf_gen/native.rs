@@ -0,0 +1,386 @@
+//! A minimal in-process PDF renderer for simple worksheets.
+//!
+//! This is not a browser engine: stylesheets are ignored, and the HTML is flattened into
+//! headings, paragraphs, list items, and preformatted blocks laid out in the PDF base-14
+//! fonts. It's good enough for text-heavy worksheets on machines without weasyprint.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str};
+
+use super::PdfBackend;
+
+// US Letter in points
+const PAGE_WIDTH: f32 = 612.0;
+const PAGE_HEIGHT: f32 = 792.0;
+const MARGIN: f32 = 54.0;
+const LIST_INDENT: f32 = 18.0;
+
+pub struct Native;
+
+impl PdfBackend for Native {
+    fn check_available(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn generate_pdf(&self, html: &str, output: &Path) -> Result<()> {
+        let blocks = extract_blocks(html);
+        std::fs::write(output, render(&blocks))
+            .context(format!("Failed to write PDF to {}", output.display()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BlockKind {
+    Heading(u8),
+    Paragraph,
+    ListItem,
+    Preformatted,
+    PageBreak,
+}
+
+#[derive(Debug)]
+struct Block {
+    kind: BlockKind,
+    text: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Font {
+    Regular,
+    Bold,
+    Mono,
+}
+
+impl Font {
+    const ALL: [Font; 3] = [Font::Regular, Font::Bold, Font::Mono];
+
+    fn resource_name(self) -> Name<'static> {
+        match self {
+            Font::Regular => Name(b"F1"),
+            Font::Bold => Name(b"F2"),
+            Font::Mono => Name(b"F3"),
+        }
+    }
+
+    fn base_font(self) -> Name<'static> {
+        match self {
+            Font::Regular => Name(b"Helvetica"),
+            Font::Bold => Name(b"Helvetica-Bold"),
+            Font::Mono => Name(b"Courier"),
+        }
+    }
+
+    /// Approximate advance width in points.
+    fn text_width(self, text: &str, size: f32) -> f32 {
+        let units: f32 = match self {
+            Font::Mono => 600.0 * text.chars().count() as f32,
+            Font::Regular => text.chars().map(helvetica_width).sum(),
+            Font::Bold => text.chars().map(helvetica_width).sum::<f32>() * 1.05,
+        };
+        units * size / 1000.0
+    }
+}
+
+/// Flatten an HTML document into a sequence of text blocks.
+fn extract_blocks(html: &str) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut current = Block {
+        kind: BlockKind::Paragraph,
+        text: String::new(),
+    };
+    // Depth inside elements whose text shouldn't be rendered
+    let mut skip_depth = 0usize;
+    let mut rest = html;
+
+    while let Some(tag_start) = rest.find('<') {
+        if skip_depth == 0 {
+            current.text.push_str(&decode_entities(&rest[..tag_start]));
+        }
+        let Some(tag_length) = rest[tag_start..].find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[tag_start + 1..tag_start + tag_length];
+        rest = &rest[tag_start + tag_length + 1..];
+
+        if tag.starts_with('!') {
+            continue;
+        }
+        let closing = tag.starts_with('/');
+        let self_closing = tag.ends_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+
+        match name.as_str() {
+            "head" | "style" | "script" | "svg" | "title" if !self_closing => {
+                if closing {
+                    skip_depth = skip_depth.saturating_sub(1);
+                } else {
+                    skip_depth += 1;
+                }
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                let level = name[1..].parse().unwrap_or(6);
+                flush_block(
+                    &mut blocks,
+                    &mut current,
+                    BlockKind::Heading(level),
+                    closing,
+                );
+            }
+            "li" => flush_block(&mut blocks, &mut current, BlockKind::ListItem, closing),
+            "pre" => flush_block(&mut blocks, &mut current, BlockKind::Preformatted, closing),
+            "div" if !closing && tag.contains("page-break") => {
+                flush_block(&mut blocks, &mut current, BlockKind::Paragraph, true);
+                blocks.push(Block {
+                    kind: BlockKind::PageBreak,
+                    text: String::new(),
+                });
+            }
+            "p" | "div" | "section" | "tr" | "table" | "ul" | "ol" | "dl" | "dt" | "dd"
+            | "blockquote" | "br" | "hr" => {
+                flush_block(&mut blocks, &mut current, BlockKind::Paragraph, closing)
+            }
+            "td" | "th" if !closing && !current.text.trim().is_empty() => {
+                current.text.push_str("    ");
+            }
+            "span" if !closing && tag.contains("answer-blank") => {
+                current.text.push_str(" __________ ");
+            }
+            _ => {}
+        }
+    }
+    if skip_depth == 0 {
+        current.text.push_str(&decode_entities(rest));
+    }
+    flush_block(&mut blocks, &mut current, BlockKind::Paragraph, true);
+
+    blocks
+}
+
+/// Finish the current block, and start a new one of the given kind.
+///
+/// After a closing tag, the new block reverts to a plain paragraph.
+fn flush_block(blocks: &mut Vec<Block>, current: &mut Block, kind: BlockKind, closing: bool) {
+    let next_kind = if closing { BlockKind::Paragraph } else { kind };
+    let finished = std::mem::replace(
+        current,
+        Block {
+            kind: next_kind,
+            text: String::new(),
+        },
+    );
+    if !finished.text.trim().is_empty() {
+        blocks.push(finished);
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+/// Lays out lines of text top to bottom, starting new pages as needed.
+struct PageWriter {
+    pages: Vec<Vec<u8>>,
+    content: Content,
+    y: f32,
+}
+
+impl PageWriter {
+    fn new() -> Self {
+        PageWriter {
+            pages: vec![],
+            content: Content::new(),
+            y: PAGE_HEIGHT - MARGIN,
+        }
+    }
+
+    fn new_page(&mut self) {
+        let content = std::mem::replace(&mut self.content, Content::new());
+        self.pages.push(content.finish());
+        self.y = PAGE_HEIGHT - MARGIN;
+    }
+
+    fn space(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    fn line(&mut self, font: Font, size: f32, indent: f32, text: &str) {
+        let line_height = size * 1.4;
+        if self.y - line_height < MARGIN {
+            self.new_page();
+        }
+        self.y -= line_height;
+        self.content
+            .begin_text()
+            .set_font(font.resource_name(), size)
+            .next_line(MARGIN + indent, self.y)
+            .show(Str(&encode_win_ansi(text)))
+            .end_text();
+    }
+
+    fn finish(mut self) -> Vec<Vec<u8>> {
+        self.new_page();
+        self.pages
+    }
+}
+
+fn render(blocks: &[Block]) -> Vec<u8> {
+    let mut writer = PageWriter::new();
+    for block in blocks {
+        let (font, size) = match block.kind {
+            BlockKind::Heading(1) => (Font::Bold, 20.0),
+            BlockKind::Heading(2) => (Font::Bold, 16.0),
+            BlockKind::Heading(_) => (Font::Bold, 13.0),
+            BlockKind::Preformatted => (Font::Mono, 10.0),
+            BlockKind::Paragraph | BlockKind::ListItem => (Font::Regular, 11.0),
+            BlockKind::PageBreak => {
+                writer.new_page();
+                continue;
+            }
+        };
+
+        match block.kind {
+            BlockKind::Preformatted => {
+                for line in block.text.trim_matches('\n').lines() {
+                    writer.line(font, size, 0.0, line);
+                }
+            }
+            BlockKind::ListItem => {
+                let lines = wrap(
+                    &block.text,
+                    font,
+                    size,
+                    PAGE_WIDTH - 2.0 * MARGIN - LIST_INDENT,
+                );
+                for (i, line) in lines.iter().enumerate() {
+                    if i == 0 {
+                        writer.line(font, size, 0.0, &format!("\u{2022} {line}"));
+                    } else {
+                        writer.line(font, size, LIST_INDENT, line);
+                    }
+                }
+            }
+            _ => {
+                if let BlockKind::Heading(_) = block.kind {
+                    writer.space(size * 0.5);
+                }
+                for line in wrap(&block.text, font, size, PAGE_WIDTH - 2.0 * MARGIN) {
+                    writer.line(font, size, 0.0, &line);
+                }
+            }
+        }
+        writer.space(size * 0.5);
+    }
+    let pages = writer.finish();
+
+    let mut pdf = Pdf::new();
+    let catalog_id = Ref::new(1);
+    let page_tree_id = Ref::new(2);
+    let font_ids: Vec<Ref> = (0..Font::ALL.len() as i32)
+        .map(|i| Ref::new(3 + i))
+        .collect();
+    let first_page_id = 3 + Font::ALL.len() as i32;
+    let page_ids: Vec<Ref> = (0..pages.len() as i32)
+        .map(|i| Ref::new(first_page_id + 2 * i))
+        .collect();
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().copied())
+        .count(page_ids.len() as i32);
+
+    for (font, &id) in Font::ALL.iter().zip(&font_ids) {
+        pdf.type1_font(id)
+            .base_font(font.base_font())
+            .encoding_predefined(Name(b"WinAnsiEncoding"));
+    }
+
+    for (content, &page_id) in pages.iter().zip(&page_ids) {
+        let content_id = Ref::new(page_id.get() + 1);
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, PAGE_WIDTH, PAGE_HEIGHT));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        {
+            let mut resources = page.resources();
+            let mut fonts = resources.fonts();
+            for (font, &id) in Font::ALL.iter().zip(&font_ids) {
+                fonts.pair(font.resource_name(), id);
+            }
+        }
+        page.finish();
+        pdf.stream(content_id, content);
+    }
+
+    pdf.finish()
+}
+
+fn wrap(text: &str, font: Font, size: f32, max_width: f32) -> Vec<String> {
+    let mut lines = vec![];
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && font.text_width(&format!("{line} {word}"), size) > max_width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Encode text for the standard fonts, replacing anything unrepresentable.
+fn encode_win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            '\u{2022}' => 0x95,
+            '\u{2013}' => 0x96,
+            '\u{2014}' => 0x97,
+            '\u{2018}' => 0x91,
+            '\u{2019}' => 0x92,
+            '\u{201C}' => 0x93,
+            '\u{201D}' => 0x94,
+            '\u{2026}' => 0x85,
+            '\u{2212}' => b'-',
+            c if (c as u32) < 0x80 || (0xA0..=0xFF).contains(&(c as u32)) => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+/// Helvetica glyph widths from the standard AFM metrics, in 1/1000 em.
+fn helvetica_width(c: char) -> f32 {
+    const ASCII_WIDTHS: [u16; 95] = [
+        278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278,
+        278, // ' '-'/'
+        556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584,
+        556, // '0'-'?'
+        1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722,
+        778, // '@'-'O'
+        667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469,
+        556, // 'P'-'_'
+        333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556,
+        556, // '`'-'o'
+        556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584, // 'p'-'~'
+    ];
+    match c as u32 {
+        code @ 32..=126 => ASCII_WIDTHS[(code - 32) as usize] as f32,
+        _ => 556.0,
+    }
+}
@@ -0,0 +1,106 @@
+mod impose;
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::config::{Encryption, Options, Permission};
+use crate::pdf_gen;
+
+/// Fail early if a post-processing step needs a program that isn't installed.
+pub fn check_available(options: &Options) -> Result<()> {
+    if options.encryption.is_some() || options.prepend_pdf.is_some() || options.append_pdf.is_some()
+    {
+        pdf_gen::check_executable("qpdf")?;
+    }
+    Ok(())
+}
+
+/// Apply the configured post-processing steps to a freshly generated PDF, in place.
+pub fn postprocess(options: &Options, pdf: &Path) -> Result<()> {
+    if options.prepend_pdf.is_some() || options.append_pdf.is_some() {
+        merge(
+            pdf,
+            options.prepend_pdf.as_deref(),
+            options.append_pdf.as_deref(),
+        )?;
+    }
+    if options.booklet {
+        impose::booklet(pdf)?;
+    }
+    if let Some(pages_per_sheet) = options.nup {
+        impose::nup(pdf, pages_per_sheet)?;
+    }
+    // Encryption comes last, since the other steps can't read encrypted PDFs
+    if let Some(encryption) = &options.encryption {
+        encrypt(pdf, encryption)?;
+    }
+    Ok(())
+}
+
+// Add the pages of other PDFs before and after the document's own
+fn merge(pdf: &Path, prepend: Option<&Path>, append: Option<&Path>) -> Result<()> {
+    let mut command = Command::new("qpdf");
+    command.arg(pdf).arg("--replace-input").arg("--pages");
+    if let Some(prepend) = prepend {
+        command.arg(prepend);
+    }
+    command.arg("."); // All of the input's pages
+    if let Some(append) = append {
+        command.arg(append);
+    }
+    command.arg("--");
+    run_qpdf(command, None)
+}
+
+fn encrypt(pdf: &Path, encryption: &Encryption) -> Result<()> {
+    let owner_password = encryption
+        .owner_password
+        .as_deref()
+        .unwrap_or(&encryption.password);
+    let allowed = |permission, yes, no| {
+        if encryption.allow.contains(&permission) {
+            yes
+        } else {
+            no
+        }
+    };
+
+    // The arguments are read from stdin (`@-`), so the passwords don't show up in `ps`
+    let arguments = [
+        "--encrypt",
+        encryption.password.as_str(),
+        owner_password,
+        "256",
+        allowed(Permission::Print, "--print=full", "--print=none"),
+        allowed(Permission::Copy, "--extract=y", "--extract=n"),
+        allowed(Permission::Modify, "--modify=all", "--modify=none"),
+        allowed(Permission::Annotate, "--annotate=y", "--annotate=n"),
+        "--",
+    ];
+    let mut command = Command::new("qpdf");
+    command.arg(pdf).arg("--replace-input").arg("@-");
+    let input = arguments.join("\n") + "\n";
+    run_qpdf(command, Some(input.as_str()))
+}
+
+// `input` is written to qpdf's stdin
+fn run_qpdf(mut command: Command, input: Option<&str>) -> Result<()> {
+    if input.is_some() {
+        command.stdin(Stdio::piped());
+    }
+    let mut child = command.spawn().context("Failed to spawn qpdf")?;
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        stdin
+            .write_all(input.as_bytes())
+            .context("Failed to write to qpdf")?;
+    }
+    let status = child.wait().context("qpdf failed")?;
+    // Exit status 3 means it succeeded with warnings
+    if !matches!(status.code(), Some(0 | 3)) {
+        return Err(anyhow!("qpdf failed"));
+    }
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, dictionary};
+
+/// How pages are arranged on each printed sheet.
+struct Layout {
+    columns: usize,
+    rows: usize,
+    /// Turn the sheet sideways relative to the original pages
+    landscape: bool,
+    /// Draw lines between the pages for cutting
+    separators: bool,
+}
+
+/// Reorder and 2-up the pages so the printed, folded stack reads as a saddle-stitched booklet.
+pub fn booklet(pdf: &Path) -> Result<()> {
+    let mut doc = load(pdf)?;
+    let sides = booklet_sides(doc.get_pages().len());
+
+    let layout = Layout {
+        columns: 2,
+        rows: 1,
+        landscape: true,
+        separators: false,
+    };
+    impose(&mut doc, &layout, sides)?;
+    save(doc, pdf)
+}
+
+// The pages on each side of each sheet, padded with blank pages so every sheet is fully used
+fn booklet_sides(page_count: usize) -> Vec<Vec<Option<usize>>> {
+    let padded = page_count.div_ceil(4) * 4;
+    let page = |index: usize| (index < page_count).then_some(index);
+    (0..padded / 2)
+        .map(|side| {
+            let (inner, outer) = (page(side), page(padded - 1 - side));
+            if side % 2 == 0 {
+                vec![outer, inner]
+            } else {
+                vec![inner, outer]
+            }
+        })
+        .collect()
+}
+
+/// Put `pages_per_sheet` (2 or 4) pages on each sheet, in order.
+pub fn nup(pdf: &Path, pages_per_sheet: u8) -> Result<()> {
+    let layout = match pages_per_sheet {
+        2 => Layout {
+            columns: 2,
+            rows: 1,
+            landscape: true,
+            separators: true,
+        },
+        4 => Layout {
+            columns: 2,
+            rows: 2,
+            landscape: false,
+            separators: true,
+        },
+        _ => return Err(anyhow!("Can't put {pages_per_sheet} pages on a sheet")),
+    };
+
+    let mut doc = load(pdf)?;
+    let page_count = doc.get_pages().len();
+    let sheets = (0..page_count)
+        .map(Some)
+        .collect::<Vec<_>>()
+        .chunks(layout.columns * layout.rows)
+        .map(<[_]>::to_vec)
+        .collect();
+    impose(&mut doc, &layout, sheets)?;
+    save(doc, pdf)
+}
+
+/// Replace the pages of `doc` with new sheets. Each sheet lists the indexes of the original pages
+/// on it in reading order, with `None` for a blank spot.
+fn impose(doc: &mut Document, layout: &Layout, sheets: Vec<Vec<Option<usize>>>) -> Result<()> {
+    let page_ids: Vec<ObjectId> = doc.get_pages().into_values().collect();
+    let forms = page_ids
+        .iter()
+        .map(|&page_id| page_form(doc, page_id))
+        .collect::<Result<Vec<_>>>()?;
+    // Sheets are the size of the first page
+    let (_, first_box) = forms.first().ok_or_else(|| anyhow!("PDF has no pages"))?;
+    let (width, height) = size(first_box);
+    let (sheet_width, sheet_height) = if layout.landscape {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    let cell_width = sheet_width / layout.columns as f32;
+    let cell_height = sheet_height / layout.rows as f32;
+
+    let pages_id = doc.catalog()?.get(b"Pages")?.as_reference()?;
+    let mut kids: Vec<Object> = vec![];
+    for sheet in sheets {
+        let mut content = String::new();
+        let mut xobjects = Dictionary::new();
+        for (slot, page) in sheet.into_iter().enumerate() {
+            let Some(page) = page else { continue };
+            let (form_id, media_box) = forms[page];
+            let (width, height) = size(&media_box);
+            let (column, row) = (slot % layout.columns, slot / layout.columns);
+            // Scale to fit the cell, and center it there
+            let scale = (cell_width / width).min(cell_height / height);
+            let x = column as f32 * cell_width + (cell_width - width * scale) / 2.0
+                - media_box[0] * scale;
+            let y = sheet_height - (row + 1) as f32 * cell_height
+                + (cell_height - height * scale) / 2.0
+                - media_box[1] * scale;
+            writeln!(content, "q {scale} 0 0 {scale} {x} {y} cm /P{page} Do Q")?;
+            xobjects.set(format!("P{page}"), form_id);
+        }
+        if layout.separators {
+            content.push_str("0.5 w 0.6 G\n");
+            for column in 1..layout.columns {
+                let x = column as f32 * cell_width;
+                writeln!(content, "{x} 0 m {x} {sheet_height} l S")?;
+            }
+            for row in 1..layout.rows {
+                let y = row as f32 * cell_height;
+                writeln!(content, "0 {y} m {sheet_width} {y} l S")?;
+            }
+        }
+
+        let content_id = doc.add_object(Stream::new(Dictionary::new(), content.into_bytes()));
+        let sheet_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), sheet_width.into(), sheet_height.into()],
+            "Resources" => dictionary! { "XObject" => xobjects },
+            "Contents" => content_id,
+        });
+        kids.push(sheet_id.into());
+    }
+
+    let pages = doc.get_object_mut(pages_id)?.as_dict_mut()?;
+    pages.set("Count", kids.len() as i64);
+    pages.set("Kids", kids);
+    // Bookmarks point at the original pages, which are no longer in the document
+    let root_id = doc.trailer.get(b"Root")?.as_reference()?;
+    doc.get_object_mut(root_id)?
+        .as_dict_mut()?
+        .remove(b"Outlines");
+    doc.prune_objects();
+
+    Ok(())
+}
+
+/// Copy a page into a form XObject that can be drawn on another page, returning its id along
+/// with the page's media box.
+fn page_form(doc: &mut Document, page_id: ObjectId) -> Result<(ObjectId, [f32; 4])> {
+    let content = doc.get_page_content(page_id)?;
+    let media_box: Vec<f32> = inherited(doc, page_id, b"MediaBox")?
+        .ok_or_else(|| anyhow!("Page has no MediaBox"))?
+        .as_array()?
+        .iter()
+        .map(Object::as_float)
+        .collect::<Result<_, _>>()?;
+    let media_box: [f32; 4] = media_box
+        .try_into()
+        .map_err(|_| anyhow!("Invalid page MediaBox"))?;
+    let resources =
+        inherited(doc, page_id, b"Resources")?.unwrap_or_else(|| Dictionary::new().into());
+
+    let form = Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => media_box.iter().map(|&n| n.into()).collect::<Vec<Object>>(),
+            "Resources" => resources,
+        },
+        content,
+    );
+    Ok((doc.add_object(form), media_box))
+}
+
+// Attributes like the media box may be set on any ancestor in the page tree
+fn inherited(doc: &Document, page_id: ObjectId, key: &[u8]) -> Result<Option<Object>> {
+    let mut node = doc.get_dictionary(page_id)?;
+    loop {
+        if let Ok(value) = node.get(key) {
+            return Ok(Some(value.clone()));
+        }
+        match node.get(b"Parent") {
+            Ok(parent) => node = doc.get_dictionary(parent.as_reference()?)?,
+            Err(_) => return Ok(None),
+        }
+    }
+}
+
+fn size(media_box: &[f32; 4]) -> (f32, f32) {
+    (media_box[2] - media_box[0], media_box[3] - media_box[1])
+}
+
+fn load(pdf: &Path) -> Result<Document> {
+    Document::load(pdf).context(format!("Failed to read PDF {}", pdf.display()))
+}
+
+fn save(mut doc: Document, pdf: &Path) -> Result<()> {
+    doc.compress();
+    doc.save(pdf)
+        .context(format!("Failed to write PDF {}", pdf.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Page numbers from one, with 0 for a blank page
+    fn page_numbers(sides: Vec<Vec<Option<usize>>>) -> Vec<Vec<usize>> {
+        sides
+            .into_iter()
+            .map(|side| {
+                side.into_iter()
+                    .map(|page| page.map_or(0, |i| i + 1))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn booklet_folds_into_order() {
+        assert_eq!(
+            page_numbers(booklet_sides(8)),
+            [[8, 1], [2, 7], [6, 3], [4, 5]]
+        );
+    }
+
+    #[test]
+    fn booklet_pads_with_blank_pages() {
+        assert_eq!(page_numbers(booklet_sides(3)), [[0, 1], [2, 3]]);
+        assert_eq!(
+            page_numbers(booklet_sides(5)),
+            [[0, 1], [2, 0], [0, 3], [4, 5]]
+        );
+        assert_eq!(
+            page_numbers(booklet_sides(6)),
+            [[0, 1], [2, 0], [6, 3], [4, 5]]
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::pdf_gen::{self, PdfBackend};
+
+const RESOLUTION_DPI: u32 = 150;
+
+// Files are built in parallel, so each needs its own work directory
+static WORK_DIRS: AtomicUsize = AtomicUsize::new(0);
+
+pub fn check_available() -> Result<()> {
+    pdf_gen::check_executable("pdftoppm")
+}
+
+/// Render the document to PDF, then rasterize each page to `<stem>-<n>.png` next to `output`.
+pub fn generate_png(backend: &dyn PdfBackend, html: &str, output: &Path) -> Result<Vec<PathBuf>> {
+    let work_dir = std::env::temp_dir().join(format!(
+        "worksheet-generator-{}-{}",
+        std::process::id(),
+        WORK_DIRS.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::create_dir_all(&work_dir).context(format!(
+        "Failed to create temporary directory: {}",
+        work_dir.display()
+    ))?;
+    let result = rasterize(backend, html, output, &work_dir);
+    let _ = std::fs::remove_dir_all(&work_dir);
+    result
+}
+
+fn rasterize(
+    backend: &dyn PdfBackend,
+    html: &str,
+    output: &Path,
+    work_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let pdf_path = work_dir.join("document.pdf");
+    backend.generate_pdf(html, &pdf_path)?;
+
+    let status = Command::new("pdftoppm")
+        .arg("-png")
+        .arg("-r")
+        .arg(RESOLUTION_DPI.to_string())
+        .arg(&pdf_path)
+        .arg(work_dir.join("page"))
+        .status()
+        .context("Failed to spawn pdftoppm")?;
+    if !status.success() {
+        return Err(anyhow!("pdftoppm failed"));
+    }
+
+    // pdftoppm zero-pads page numbers depending on the page count, so rename to a
+    // predictable scheme rather than relying on its output names.
+    let mut page_images: Vec<PathBuf> = std::fs::read_dir(work_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+        .collect();
+    page_images.sort();
+
+    let stem = output
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
+    let mut outputs = vec![];
+    for (i, image) in page_images.iter().enumerate() {
+        let destination = output.with_file_name(format!("{stem}-{}.png", i + 1));
+        // Copy rather than rename in case the temp directory is on another filesystem
+        std::fs::copy(image, &destination)
+            .context(format!("Failed to write PNG to {}", destination.display()))?;
+        outputs.push(destination);
+    }
+
+    Ok(outputs)
+}
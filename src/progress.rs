@@ -0,0 +1,68 @@
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use indicatif::{ProgressBar, ProgressStyle};
+
+// Output is printed from deep inside the build, and has to go around the bar
+static BAR: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+// Set once a document is written to stdout, so status lines don't end up mixed in with it
+static STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Print status lines to stderr rather than stdout, for as long as `stderr` is set.
+pub fn set_stderr(stderr: bool) {
+    STDERR.store(stderr, Ordering::Relaxed);
+}
+
+/// Show a progress bar on stderr for building `len` files. A single file doesn't get one.
+pub fn start(len: usize) {
+    if len < 2 {
+        return;
+    }
+    let style = ProgressStyle::with_template("{bar:40} {pos}/{len} {wide_msg}")
+        .expect("Progress bar template is valid");
+    let bar = ProgressBar::new(len as u64).with_style(style);
+    *BAR.lock().unwrap() = Some(bar);
+}
+
+/// Show which file is being built.
+pub fn set_message(message: String) {
+    if let Some(bar) = &*BAR.lock().unwrap() {
+        bar.set_message(message);
+    }
+}
+
+/// Count a file as done.
+pub fn inc() {
+    if let Some(bar) = &*BAR.lock().unwrap() {
+        bar.inc(1);
+    }
+}
+
+/// Remove the progress bar.
+pub fn finish() {
+    if let Some(bar) = BAR.lock().unwrap().take() {
+        bar.finish_and_clear();
+    }
+}
+
+/// Print a status line above the progress bar, if there is one.
+///
+/// Lines go to stdout, unless a document is being written there.
+pub fn println(line: &str) {
+    match &*BAR.lock().unwrap() {
+        Some(bar) if !bar.is_hidden() => bar.println(line),
+        _ if STDERR.load(Ordering::Relaxed) => eprintln!("{line}"),
+        _ => println!("{line}"),
+    }
+}
+
+/// Run `f` with the progress bar hidden, so it can write to stderr.
+pub fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    // Cloned so `f` can print lines itself
+    let bar = BAR.lock().unwrap().clone();
+    match bar {
+        Some(bar) => bar.suspend(f),
+        None => f(),
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::{IndexedRandom, SliceRandom};
+use serde::Deserialize;
+
+use crate::inline::render_inline_answer;
+use crate::warnings::Warnings;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuestionBank {
+    pub questions: Vec<Question>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Question {
+    pub id: Option<String>,
+    pub question: String,
+    pub answer: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl QuestionBank {
+    /// Load a bank from a YAML or TOML file, chosen by extension.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .context(format!("Failed to read question bank: {}", path.display()))?;
+        let bank = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&contents)?,
+            _ => serde_yaml::from_str(&contents)?,
+        };
+        Ok(bank)
+    }
+
+    /// A Tera function, `bank(tags=[...], count=N)`, that renders randomly chosen questions as
+    /// an ordered list. Questions aren't repeated between calls.
+    ///
+    /// The ids of the chosen questions (or their position in the bank, if they don't have
+    /// one) are added to `chosen_ids`. Asking for more questions than there are is reported to
+    /// `warnings`.
+    pub fn function(
+        self,
+        seed: u64,
+        show_answers: bool,
+        chosen_ids: Arc<Mutex<Vec<String>>>,
+        warnings: Warnings,
+    ) -> impl tera::Function {
+        let state = Mutex::new((StdRng::seed_from_u64(seed), HashSet::<usize>::new()));
+
+        move |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
+            let tags: Vec<String> = match args.get("tags") {
+                Some(tera::Value::String(tag)) => vec![tag.clone()],
+                Some(tera::Value::Array(tags)) => tags
+                    .iter()
+                    .filter_map(|tag| tag.as_str().map(String::from))
+                    .collect(),
+                Some(_) => return Err(tera::Error::msg("bank: `tags` must be a list of strings")),
+                None => vec![],
+            };
+            let count = match args.get("count") {
+                Some(count) => Some(count.as_u64().ok_or_else(|| {
+                    tera::Error::msg("bank: `count` must be a non-negative integer")
+                })? as usize),
+                None => None,
+            };
+
+            let mut state = state.lock().unwrap();
+            let (rng, used) = &mut *state;
+            let candidates: Vec<usize> = (0..self.questions.len())
+                .filter(|index| !used.contains(index))
+                .filter(|&index| {
+                    let question = &self.questions[index];
+                    tags.iter().all(|tag| question.tags.contains(tag))
+                })
+                .collect();
+            let count = count.unwrap_or(candidates.len());
+            if count > candidates.len() {
+                warnings
+                    .warn(
+                        format!(
+                            "Requested {count} questions tagged {tags:?} but only {} are available",
+                            candidates.len()
+                        ),
+                        "using all of them",
+                    )
+                    .map_err(|e| tera::Error::msg(e.to_string()))?;
+            }
+
+            let mut chosen: Vec<usize> = candidates.choose_multiple(rng, count).copied().collect();
+            // choose_multiple doesn't guarantee a random order
+            chosen.shuffle(rng);
+            used.extend(&chosen);
+            chosen_ids
+                .lock()
+                .unwrap()
+                .extend(chosen.iter().map(|&index| {
+                    self.questions[index]
+                        .id
+                        .clone()
+                        .unwrap_or_else(|| format!("#{}", index + 1))
+                }));
+
+            let list: String = chosen
+                .iter()
+                .enumerate()
+                .map(|(i, &index)| {
+                    let question = &self.questions[index];
+                    let answer = match &question.answer {
+                        Some(answer) => render_inline_answer(answer, show_answers),
+                        None => String::new(),
+                    };
+                    format!("{}. {} {answer}\n", i + 1, question.question)
+                })
+                .collect();
+
+            Ok(tera::Value::String(list))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use tera::Function;
+
+    use super::*;
+
+    // Ten questions, with the even numbered ones tagged "even"
+    fn bank() -> QuestionBank {
+        let questions = (1..=10)
+            .map(|n| Question {
+                id: Some(format!("q{n}")),
+                question: format!("Question {n}"),
+                answer: Some(n.to_string()),
+                tags: if n % 2 == 0 {
+                    vec!["even".to_string()]
+                } else {
+                    vec![]
+                },
+            })
+            .collect();
+        QuestionBank { questions }
+    }
+
+    // The ids chosen by each call, with `args` as JSON
+    fn choose(seed: u64, strict: bool, calls: &[tera::Value]) -> tera::Result<Vec<Vec<String>>> {
+        let chosen_ids = Arc::new(Mutex::new(vec![]));
+        let function = bank().function(seed, false, chosen_ids.clone(), Warnings::new(strict));
+        let mut chosen = vec![];
+        for args in calls {
+            let args: HashMap<String, tera::Value> = serde_json::from_value(args.clone()).unwrap();
+            function.call(&args)?;
+            chosen.push(std::mem::take(&mut *chosen_ids.lock().unwrap()));
+        }
+        Ok(chosen)
+    }
+
+    #[test]
+    fn chooses_count_questions() {
+        let chosen = choose(1, false, &[serde_json::json!({ "count": 3 })]).unwrap();
+        assert_eq!(chosen[0].len(), 3);
+    }
+
+    #[test]
+    fn renders_an_ordered_list() {
+        let function = bank().function(1, true, Arc::default(), Warnings::default());
+        let args = HashMap::from([("count".to_string(), tera::Value::from(2))]);
+        let list = function.call(&args).unwrap();
+        let lines: Vec<&str> = list.as_str().unwrap().lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("1. Question "));
+        assert!(lines[1].starts_with("2. Question "));
+        assert!(lines[0].contains(r#"<span class="answer">"#));
+    }
+
+    #[test]
+    fn filters_by_tag() {
+        let chosen = choose(1, false, &[serde_json::json!({ "tags": ["even"] })]).unwrap();
+        let mut ids = chosen[0].clone();
+        ids.sort();
+        assert_eq!(ids, ["q10", "q2", "q4", "q6", "q8"]);
+    }
+
+    #[test]
+    fn doesnt_repeat_questions_between_calls() {
+        let chosen = choose(
+            1,
+            false,
+            &[
+                serde_json::json!({ "tags": "even", "count": 3 }),
+                serde_json::json!({ "count": 7 }),
+            ],
+        )
+        .unwrap();
+        let ids: HashSet<&String> = chosen.iter().flatten().collect();
+        assert_eq!(ids.len(), 10);
+    }
+
+    #[test]
+    fn same_seed_chooses_same_questions() {
+        let calls = [
+            serde_json::json!({ "count": 4 }),
+            serde_json::json!({ "tags": "even", "count": 2 }),
+        ];
+        assert_eq!(
+            choose(7, false, &calls).unwrap(),
+            choose(7, false, &calls).unwrap()
+        );
+    }
+
+    #[test]
+    fn too_few_questions_uses_them_all() {
+        let chosen = choose(
+            1,
+            false,
+            &[serde_json::json!({ "tags": "even", "count": 6 })],
+        );
+        assert_eq!(chosen.unwrap()[0].len(), 5);
+    }
+
+    #[test]
+    fn too_few_questions_is_an_error_in_strict_mode() {
+        let result = choose(
+            1,
+            true,
+            &[serde_json::json!({ "tags": "even", "count": 6 })],
+        );
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("Requested 6 questions"), "{error}");
+    }
+
+    #[test]
+    fn uses_ids_or_positions() {
+        let mut bank = bank();
+        bank.questions.truncate(2);
+        bank.questions[1].id = None;
+        let chosen_ids = Arc::new(Mutex::new(vec![]));
+        let function = bank.function(1, false, chosen_ids.clone(), Warnings::default());
+        function.call(&HashMap::new()).unwrap();
+        let mut ids = chosen_ids.lock().unwrap().clone();
+        ids.sort();
+        assert_eq!(ids, ["#2", "q1"]);
+    }
+}
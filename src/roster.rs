@@ -0,0 +1,86 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use slug::slugify;
+use tera::Tera;
+
+use crate::config::{Options, with_stem_suffix};
+
+pub type Student = BTreeMap<String, String>;
+
+pub struct Roster {
+    pub columns: Vec<String>,
+    pub students: Vec<Student>,
+}
+
+impl Roster {
+    /// Load a CSV roster with a header row, one student per row.
+    pub fn load(roster_path: &Path) -> Result<Self> {
+        let mut reader = csv::Reader::from_path(roster_path)
+            .context(format!("Failed to open roster: {}", roster_path.display()))?;
+        let columns: Vec<String> = reader.headers()?.iter().map(String::from).collect();
+
+        let students = reader
+            .records()
+            .map(|record| {
+                let record =
+                    record.context(format!("Failed to parse roster: {}", roster_path.display()))?;
+                Ok(columns
+                    .iter()
+                    .cloned()
+                    .zip(record.iter().map(String::from))
+                    .collect())
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Roster { columns, students })
+    }
+
+    /// A student with every column blank, used to render the shared answer key.
+    pub fn blank_student(&self) -> Student {
+        self.columns
+            .iter()
+            .map(|column| (column.clone(), String::new()))
+            .collect()
+    }
+
+    /// Options for rendering one student's copy, exposing their row as the `student` variable.
+    pub fn student_options(&self, options: &Options, index: usize) -> Result<Options> {
+        let student = &self.students[index];
+        let mut student_options = options.clone();
+        student_options
+            .variables
+            .insert("student".to_string(), serde_yaml::to_value(student)?);
+        student_options.output_file = self.student_output_file(options, index)?;
+        student_options.answer_key_file = None;
+
+        Ok(student_options)
+    }
+
+    fn student_output_file(&self, options: &Options, index: usize) -> Result<PathBuf> {
+        let student = &self.students[index];
+        let file_name = match &options.roster_output_name {
+            Some(template) => {
+                let mut context = tera::Context::new();
+                context.insert("student", student);
+                context.insert("index", &(index + 1));
+                Tera::one_off(template, &context, false)
+                    .context(format!("Failed to render roster output name: {template}"))?
+            }
+            None => {
+                // Suffix the output name with the first column, e.g. `quiz-ada-lovelace.pdf`
+                let suffix = self
+                    .columns
+                    .first()
+                    .and_then(|column| student.get(column))
+                    .map(slugify)
+                    .filter(|slug| !slug.is_empty())
+                    .unwrap_or_else(|| (index + 1).to_string());
+                return Ok(with_stem_suffix(&options.output_file, &suffix));
+            }
+        };
+
+        Ok(options.output_file.with_file_name(file_name))
+    }
+}
@@ -0,0 +1,150 @@
+use std::io::Write;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow};
+use tiny_http::{Header, Request, Response, Server};
+
+use crate::config::{Args, Options};
+use crate::html_gen;
+
+// Reconnects automatically, so a server restart doesn't require a manual refresh
+const LIVE_RELOAD_SCRIPT: &str = r#"<script>
+    new EventSource("/events").onmessage = () => location.reload();
+</script>
+"#;
+
+// Dead clients are only noticed when a write fails
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Serve a live-reloading HTML preview of the worksheet. Never returns on success.
+pub fn serve(args: Args, address: &str) -> Result<()> {
+    let server =
+        Server::http(address).map_err(|e| anyhow!("Failed to listen on {address}: {e}"))?;
+    println!("Serving preview at http://{address}/ (answer key at /key)");
+    if args.open {
+        let url = format!("http://{address}/");
+        open::that(&url).context(format!("Failed to open {url}"))?;
+    }
+
+    // Picked once, so the worksheet and its answer key match and don't change on every reload
+    let seed = rand::random();
+    let changes = Arc::new(Changes::default());
+    {
+        let args = args.clone();
+        let changes = changes.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = crate::watch::on_change(&args, || changes.notify()) {
+                eprintln!("Error: {e:#}");
+            }
+        });
+    }
+
+    for request in server.incoming_requests() {
+        let args = args.clone();
+        let changes = changes.clone();
+        std::thread::spawn(move || handle_request(request, args, seed, &changes));
+    }
+
+    Ok(())
+}
+
+fn handle_request(request: Request, args: Args, seed: u64, changes: &Changes) {
+    let url = request.url().to_string();
+    let result = match url.as_str() {
+        "/" => request.respond(html_response(render_preview(args, seed, false))),
+        "/key" => request.respond(html_response(render_preview(args, seed, true))),
+        "/events" => stream_events(request, changes),
+        _ => request.respond(Response::from_string("Not found").with_status_code(404)),
+    };
+    if let Err(e) = result {
+        eprintln!("Warning: Failed to respond to request: {e}");
+    }
+}
+
+/// Render the worksheet, using `seed` unless the command line or config sets one.
+fn render_preview(mut args: Args, seed: u64, show_answers: bool) -> String {
+    // The first target, if the config has several
+    let html = Options::load_configs(
+        args.config.as_deref(),
+        args.profile.as_deref(),
+        args.target.as_deref(),
+    )
+    .and_then(|configs| {
+        let config = configs.into_iter().next().unwrap_or_default();
+        if args.seed.is_none() && config.seed.is_none() {
+            args.seed = Some(seed);
+        }
+        let mut options = Options::from_args_and_config(args, config)?;
+        // The browser won't load file:// images from an http:// page
+        options.self_contained = true;
+        Ok(options)
+    })
+    .and_then(|options| html_gen::generate_html(&options, show_answers))
+    .unwrap_or_else(|e| {
+        let message = format!("{e:#}")
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;");
+        format!("<!DOCTYPE html>\n<html>\n<body>\n<pre>Error: {message}</pre>\n</body>\n</html>")
+    });
+
+    match html.rfind("</body>") {
+        Some(index) => format!("{}{LIVE_RELOAD_SCRIPT}{}", &html[..index], &html[index..]),
+        None => html + LIVE_RELOAD_SCRIPT,
+    }
+}
+
+fn html_response(html: String) -> Response<std::io::Cursor<Vec<u8>>> {
+    let content_type = Header::from_bytes(&b"Content-Type"[..], &b"text/html; charset=utf-8"[..])
+        .expect("Static header should be valid");
+    Response::from_string(html).with_header(content_type)
+}
+
+fn stream_events(request: Request, changes: &Changes) -> std::io::Result<()> {
+    let mut generation = changes.generation();
+    let mut writer = request.into_writer();
+    writer.write_all(
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\r\n",
+    )?;
+    writer.flush()?;
+
+    loop {
+        let latest = changes.wait(generation, KEEPALIVE_INTERVAL);
+        if latest == generation {
+            writer.write_all(b": keepalive\n\n")?;
+        } else {
+            generation = latest;
+            writer.write_all(b"data: reload\n\n")?;
+        }
+        writer.flush()?;
+    }
+}
+
+/// A counter bumped every time the worksheet's inputs change.
+#[derive(Default)]
+struct Changes {
+    generation: Mutex<u64>,
+    condvar: Condvar,
+}
+
+impl Changes {
+    fn notify(&self) {
+        *self.generation.lock().unwrap() += 1;
+        self.condvar.notify_all();
+    }
+
+    fn generation(&self) -> u64 {
+        *self.generation.lock().unwrap()
+    }
+
+    /// Wait until the generation moves past `seen` or the timeout expires.
+    fn wait(&self, seen: u64, timeout: Duration) -> u64 {
+        let guard = self.generation.lock().unwrap();
+        let (guard, _) = self
+            .condvar
+            .wait_timeout_while(guard, timeout, |generation| *generation == seen)
+            .unwrap();
+        *guard
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::config::{Args, Options, OutputFormat};
+use crate::html_gen::{escape_attribute, escape_html};
+
+const SITE_CSS: &str = r#"body { font-family: sans-serif; max-width: 50em; margin: 2em auto; padding: 0 1em; }
+ul.worksheets { list-style: none; padding: 0; }
+li.worksheet { margin: 0.5em 0; }
+.worksheet-name { font-weight: bold; margin-right: 1em; }
+.worksheet-file { margin-right: 0.7em; }
+"#;
+
+/// Build every worksheet config in a directory into a static site.
+///
+/// Each config gets an HTML and a PDF build in its own directory, and the index groups them by
+/// the subdirectory their config is in.
+pub fn build_site(args: Args) -> Result<()> {
+    let root = args.config.clone().unwrap_or_else(|| PathBuf::from("."));
+    if !root.is_dir() {
+        return Err(anyhow!(
+            "The site format needs a directory of configs, not {}",
+            root.display()
+        ));
+    }
+    if args.manifest.is_some() {
+        return Err(anyhow!("Sites can't be built with a manifest"));
+    }
+    let site_dir = args
+        .output_file
+        .clone()
+        .unwrap_or_else(|| PathBuf::from("site"));
+
+    let mut configs = vec![];
+    find_configs(&root, &site_dir, &mut configs)?;
+    if configs.is_empty() {
+        return Err(anyhow!("No worksheet configs found in {}", root.display()));
+    }
+
+    // Section name to (worksheet name, files) for each worksheet in it
+    let mut sections: BTreeMap<String, Vec<(String, Vec<PathBuf>)>> = BTreeMap::new();
+    for config in configs {
+        let relative = config.strip_prefix(&root).unwrap_or(&config);
+        let section = relative
+            .parent()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let name = config
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let worksheet_dir = site_dir.join(&section).join(&name);
+        if !args.dry_run {
+            fs::create_dir_all(&worksheet_dir).context(format!(
+                "Failed to create directory {}",
+                worksheet_dir.display()
+            ))?;
+        }
+
+        for (format, extension) in [(OutputFormat::Html, "html"), (OutputFormat::Pdf, "pdf")] {
+            let mut worksheet_args = args.clone();
+            worksheet_args.config = Some(config.clone());
+            worksheet_args.output_format = format;
+            worksheet_args.open = false;
+            worksheet_args.output_file = Some(worksheet_dir.join(format!("{name}.{extension}")));
+            crate::build(worksheet_args)
+                .context(format!("Failed to build {}", config.display()))?;
+        }
+        if args.dry_run {
+            // Nothing was written to link to
+            continue;
+        }
+
+        // Answer keys, variants, and so on are all linked
+        let mut files: Vec<PathBuf> = fs::read_dir(&worksheet_dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == "html" || ext == "pdf")
+            })
+            .collect();
+        files.sort();
+        sections.entry(section).or_default().push((name, files));
+    }
+
+    let index_path = site_dir.join("index.html");
+    if args.dry_run {
+        println!("Would write {}", index_path.display());
+        return Ok(());
+    }
+    fs::write(site_dir.join("site.css"), SITE_CSS)?;
+    fs::write(&index_path, index_html(&site_dir, &sections))
+        .context(format!("Failed to write {}", index_path.display()))?;
+    println!("✓ Site generated at {}", index_path.display());
+    if args.open {
+        crate::open_output(&index_path)?;
+    }
+
+    Ok(())
+}
+
+// Collect config files under `dir`, skipping the site being built
+fn find_configs(dir: &Path, site_dir: &Path, configs: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .context(format!("Failed to read directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden || same_path(&path, site_dir) {
+            continue;
+        }
+        if path.is_dir() {
+            find_configs(&path, site_dir, configs)?;
+        } else if is_config(&path) {
+            configs.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+// Other config-like files, like question banks, don't have any pages
+fn is_config(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext == "yaml" || ext == "yml" || ext == "toml")
+        && Options::load_configs(Some(path), None, None)
+            .is_ok_and(|configs| configs.iter().any(|config| !config.pages.is_empty()))
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn index_html(site_dir: &Path, sections: &BTreeMap<String, Vec<(String, Vec<PathBuf>)>>) -> String {
+    let mut body = String::new();
+    for (section, worksheets) in sections {
+        if !section.is_empty() {
+            body.push_str(&format!("<h2>{}</h2>\n", escape_html(section)));
+        }
+        body.push_str("<ul class=\"worksheets\">\n");
+        for (name, files) in worksheets {
+            body.push_str(&format!(
+                "<li class=\"worksheet\"><span class=\"worksheet-name\">{}</span>",
+                escape_html(name)
+            ));
+            for file in files {
+                let href = file.strip_prefix(site_dir).unwrap_or(file);
+                let href = href.to_string_lossy().replace('\\', "/");
+                let label = file
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                body.push_str(&format!(
+                    "<a class=\"worksheet-file\" href=\"{}\">{}</a>",
+                    escape_attribute(&href),
+                    escape_html(&label)
+                ));
+            }
+            body.push_str("</li>\n");
+        }
+        body.push_str("</ul>\n");
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+    <meta charset="UTF-8">
+    <title>Worksheets</title>
+    <link rel="stylesheet" href="site.css">
+</head>
+<body>
+<h1>Worksheets</h1>
+{body}</body>
+</html>"#
+    )
+}
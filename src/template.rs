@@ -0,0 +1,78 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use rand::Rng;
+use tera::Tera;
+
+use crate::config::Options;
+use crate::context::RenderContext;
+use crate::front_matter::FrontMatter;
+use crate::question_bank::QuestionBank;
+
+/// Run a page through Tera, exposing built-in variables along with those from the config
+/// and the page's front matter. Front matter takes precedence over the config.
+pub fn render(
+    markdown: &str,
+    page_path: &Path,
+    front_matter: &FrontMatter,
+    options: &Options,
+    ctx: &mut RenderContext,
+) -> Result<String> {
+    let mut context = tera::Context::new();
+
+    context.insert("date", &chrono::Local::now().format("%Y-%m-%d").to_string());
+    let page_title = front_matter
+        .title
+        .clone()
+        .or_else(|| {
+            page_path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default();
+    context.insert("page_title", &page_title);
+
+    for (name, value) in options.variables.iter().chain(&front_matter.variables) {
+        context.insert(name, value);
+    }
+
+    let mut tera = Tera::default();
+    for (name, template) in &options.shortcodes {
+        tera.register_function(name, shortcode(name.clone(), template.clone()));
+    }
+    let chosen_ids = Arc::new(Mutex::new(vec![]));
+    if let Some(bank_path) = &options.question_bank {
+        let bank = QuestionBank::load(bank_path)?;
+        let function = bank.function(
+            ctx.rng.random(),
+            ctx.show_answers,
+            chosen_ids.clone(),
+            ctx.warnings.clone(),
+        );
+        tera.register_function("bank", function);
+    }
+
+    let markdown = tera.render_str(markdown, &context).context(format!(
+        "Failed to render template in {}",
+        page_path.display()
+    ))?;
+    ctx.bank_questions
+        .extend(chosen_ids.lock().unwrap().drain(..));
+
+    Ok(markdown)
+}
+
+// A Tera function that renders a user-defined shortcode, with its arguments as variables
+fn shortcode(name: String, template: String) -> impl tera::Function {
+    move |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
+        let mut context = tera::Context::new();
+        for (arg, value) in args {
+            context.insert(arg, value);
+        }
+        let html = Tera::one_off(&template, &context, false)
+            .map_err(|e| tera::Error::chain(format!("Failed to render shortcode {name}"), e))?;
+        Ok(tera::Value::String(html))
+    }
+}
@@ -0,0 +1,56 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use tera::Tera;
+
+use crate::assets::{self, AssetMode};
+use crate::config::{Options, TitlePage};
+
+const DEFAULT_TEMPLATE: &str = r#"<div class="title-page">
+{% if logo %}<img class="title-page-logo" src="{{ logo | safe }}">{% endif %}
+{% if title %}<h1 class="title-page-title">{{ title }}</h1>{% endif %}
+{% if subtitle %}<p class="title-page-subtitle">{{ subtitle }}</p>{% endif %}
+{% if class %}<p class="title-page-class">{{ class }}</p>{% endif %}
+<p class="title-page-date">{{ date }}</p>
+</div>"#;
+
+/// Render the cover page that goes before the worksheet.
+///
+/// Templates can use `title`, `subtitle`, `class`, `date`, and `logo`, along with the
+/// variables from the config.
+pub fn render(title_page: &TitlePage, options: &Options) -> Result<String> {
+    let mut context = tera::Context::new();
+    for (name, value) in &options.variables {
+        context.insert(name, value);
+    }
+    context.insert("title", &title_page.title);
+    context.insert("subtitle", &title_page.subtitle);
+    context.insert("class", &title_page.class);
+    let date = title_page
+        .date
+        .clone()
+        .unwrap_or_else(|| chrono::Local::now().format("%Y-%m-%d").to_string());
+    context.insert("date", &date);
+    let logo = title_page
+        .logo
+        .as_ref()
+        .map(|logo| logo.display().to_string());
+    context.insert("logo", &logo);
+
+    let template = match &title_page.template {
+        Some(path) => fs::read_to_string(path).context(format!(
+            "Failed to read title page template: {}",
+            path.display()
+        ))?,
+        None => DEFAULT_TEMPLATE.to_string(),
+    };
+    let html =
+        Tera::one_off(&template, &context, true).context("Failed to render title page template")?;
+
+    // The logo path was already resolved against the config directory
+    let mode = AssetMode::Resolve {
+        cache_dir: &options.cache_dir,
+    };
+    assets::rewrite_images(&html, Path::new("."), mode, &options.warnings)
+}
@@ -0,0 +1,250 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use comrak::nodes::{AstNode, ListType, NodeValue};
+use comrak::{Arena, parse_document};
+
+use crate::assets;
+use crate::config::Options;
+use crate::context::RenderContext;
+use crate::export::{self, CodeBlock};
+use crate::page::Page;
+
+/// Convert markdown pages into a single Typst document.
+///
+/// Raw HTML has no Typst equivalent and is dropped. Answers and callouts are kept, but other
+/// generated exercises are an error.
+pub fn generate_typst(options: &Options, show_answers: bool) -> Result<String> {
+    let mut typst = String::new();
+
+    let mut ctx = RenderContext::new(options, show_answers);
+    for (i, page_path) in options.pages.iter().enumerate() {
+        let page = Page::load(page_path, options, &mut ctx)?;
+
+        let arena = Arena::new();
+        let markdown_options = ctx.markdown.clone();
+        let root = parse_document(&arena, &page.markdown, &markdown_options);
+        let page_dir = page_path.parent().unwrap_or_else(|| Path::new("."));
+        for node in root.descendants() {
+            if let NodeValue::Image(link) = &node.data.borrow().value
+                && let Some(path) = assets::local_path(&link.url, page_dir)
+                && path.is_file()
+            {
+                ctx.images.insert(path);
+            }
+        }
+
+        if i > 0 {
+            typst.push_str("#pagebreak()\n\n");
+        }
+        render_node(root, &mut typst, 0, &mut ctx)
+            .context(format!("Failed to convert {}", page_path.display()))?;
+    }
+    ctx.log_render();
+
+    Ok(typst)
+}
+
+fn render_node<'a>(
+    node: &'a AstNode<'a>,
+    out: &mut String,
+    list_depth: usize,
+    ctx: &mut RenderContext,
+) -> Result<()> {
+    match &node.data.borrow().value {
+        NodeValue::Paragraph => {
+            render_children(node, out, list_depth, ctx)?;
+            let in_list_item = node.parent().is_some_and(|parent| {
+                matches!(
+                    parent.data.borrow().value,
+                    NodeValue::Item(_) | NodeValue::TaskItem(..)
+                )
+            });
+            out.push_str(if in_list_item { "\n" } else { "\n\n" });
+        }
+        NodeValue::Heading(heading) => {
+            out.push_str(&"=".repeat(heading.level as usize));
+            out.push(' ');
+            render_children(node, out, list_depth, ctx)?;
+            out.push_str("\n\n");
+        }
+        NodeValue::Text(text) => out.push_str(&escape_markup(text)),
+        NodeValue::SoftBreak => out.push(' '),
+        NodeValue::LineBreak => out.push_str(" \\\n"),
+        NodeValue::Emph => render_wrapped(node, out, list_depth, "#emph[", ctx)?,
+        NodeValue::Strong => render_wrapped(node, out, list_depth, "#strong[", ctx)?,
+        NodeValue::Strikethrough => render_wrapped(node, out, list_depth, "#strike[", ctx)?,
+        NodeValue::Superscript => render_wrapped(node, out, list_depth, "#super[", ctx)?,
+        NodeValue::Code(code) => {
+            out.push_str(&format!("#raw({})", typst_string(&code.literal)));
+        }
+        // Typst has its own math syntax, so LaTeX is shown as written
+        NodeValue::Math(math) => {
+            out.push_str(&format!("#raw({})", typst_string(&math.literal)));
+        }
+        NodeValue::CodeBlock(block) => {
+            match export::code_block(&block.info, &block.literal, "Typst", ctx)? {
+                CodeBlock::Answer(Some(answer)) => {
+                    out.push_str("#block(fill: luma(235), inset: 8pt, width: 100%)[\n");
+                    render_markdown(answer, out, ctx)?;
+                    out.push_str("]\n\n");
+                }
+                CodeBlock::Answer(None) => out.push_str("#v(4em)\n\n"),
+                CodeBlock::Admonition { title, body } => {
+                    out.push_str(&format!(
+                        "#block(stroke: 0.5pt, inset: 8pt, width: 100%)[\n#strong[{}]\n\n",
+                        escape_markup(&title)
+                    ));
+                    render_markdown(&body, out, ctx)?;
+                    out.push_str("]\n\n");
+                }
+                CodeBlock::Code(lang) => {
+                    let lang = match lang {
+                        Some(lang) => format!("lang: {}, ", typst_string(lang)),
+                        None => String::new(),
+                    };
+                    out.push_str(&format!(
+                        "#raw(block: true, {lang}{})\n\n",
+                        typst_string(&block.literal)
+                    ));
+                }
+            }
+        }
+        NodeValue::Link(link) => {
+            out.push_str(&format!("#link({})[", typst_string(&link.url)));
+            render_children(node, out, list_depth, ctx)?;
+            out.push(']');
+        }
+        NodeValue::Image(link) => {
+            out.push_str(&format!("#image({})", typst_string(&link.url)));
+        }
+        NodeValue::List(_) => {
+            for child in node.children() {
+                render_node(child, out, list_depth + 1, ctx)?;
+            }
+            if list_depth == 0 {
+                out.push('\n');
+            }
+        }
+        NodeValue::Item(list) => {
+            let marker = match list.list_type {
+                ListType::Bullet => "-",
+                ListType::Ordered => "+",
+            };
+            out.push_str(&format!("{}{marker} ", "  ".repeat(list_depth - 1)));
+            render_children(node, out, list_depth, ctx)?;
+        }
+        NodeValue::TaskItem(..) => {
+            out.push_str(&format!("{}- ☐ ", "  ".repeat(list_depth - 1)));
+            render_children(node, out, list_depth, ctx)?;
+        }
+        NodeValue::BlockQuote => {
+            out.push_str("#quote(block: true)[\n");
+            render_children(node, out, list_depth, ctx)?;
+            out.push_str("]\n\n");
+        }
+        NodeValue::ThematicBreak => out.push_str("#line(length: 100%)\n\n"),
+        NodeValue::Table(..) => render_table(node, out, ctx)?,
+        // The answer itself is text between the span tags, but the blank needs drawing
+        NodeValue::HtmlInline(html) if export::is_answer_blank(html) => {
+            out.push_str("#box(width: 4em, stroke: (bottom: 0.5pt))");
+        }
+        NodeValue::HtmlBlock(_) | NodeValue::HtmlInline(_) | NodeValue::FrontMatter(_) => {}
+        _ => render_children(node, out, list_depth, ctx)?,
+    }
+    Ok(())
+}
+
+fn render_children<'a>(
+    node: &'a AstNode<'a>,
+    out: &mut String,
+    list_depth: usize,
+    ctx: &mut RenderContext,
+) -> Result<()> {
+    for child in node.children() {
+        render_node(child, out, list_depth, ctx)?;
+    }
+    Ok(())
+}
+
+fn render_wrapped<'a>(
+    node: &'a AstNode<'a>,
+    out: &mut String,
+    list_depth: usize,
+    open: &str,
+    ctx: &mut RenderContext,
+) -> Result<()> {
+    out.push_str(open);
+    render_children(node, out, list_depth, ctx)?;
+    out.push(']');
+    Ok(())
+}
+
+// Markdown nested in a block, like an answer or a callout's content
+fn render_markdown(markdown: &str, out: &mut String, ctx: &mut RenderContext) -> Result<()> {
+    let arena = Arena::new();
+    let markdown_options = ctx.markdown.clone();
+    let root = parse_document(&arena, markdown, &markdown_options);
+    render_node(root, out, 0, ctx)
+}
+
+fn render_table<'a>(
+    node: &'a AstNode<'a>,
+    out: &mut String,
+    ctx: &mut RenderContext,
+) -> Result<()> {
+    let columns = node
+        .first_child()
+        .map(|row| row.children().count())
+        .unwrap_or(1);
+    out.push_str(&format!("#table(\n  columns: {columns},\n"));
+    for (row_index, row) in node.children().enumerate() {
+        for cell in row.children() {
+            let mut content = String::new();
+            render_children(cell, &mut content, 0, ctx)?;
+            if row_index == 0 {
+                out.push_str(&format!("  [#strong[{}]],\n", content.trim()));
+            } else {
+                out.push_str(&format!("  [{}],\n", content.trim()));
+            }
+        }
+    }
+    out.push_str(")\n\n");
+    Ok(())
+}
+
+fn escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '#'
+                | '*'
+                | '_'
+                | '`'
+                | '$'
+                | '<'
+                | '>'
+                | '@'
+                | '['
+                | ']'
+                | '~'
+                | '/'
+                | '='
+                | '-'
+                | '+'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn typst_string(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
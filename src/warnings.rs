@@ -0,0 +1,56 @@
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Result, anyhow};
+
+use crate::progress;
+
+/// Problems a build can work around, reported as they're found.
+///
+/// Each config is built with its own strictness, but every copy made with [`Warnings::sharing`]
+/// collects into the same list. Files are built in parallel, so this travels with the options
+/// rather than living in a global.
+#[derive(Debug, Clone, Default)]
+pub struct Warnings {
+    strict: bool,
+    // The answer key and the worksheet often have the same problems, which only need
+    // reporting once
+    reported: Arc<Mutex<Vec<String>>>,
+}
+
+impl Warnings {
+    /// An empty list of warnings, which are errors instead if `strict` is set (`--strict`).
+    pub fn new(strict: bool) -> Self {
+        Warnings {
+            strict,
+            reported: Arc::default(),
+        }
+    }
+
+    /// The same strictness, but reporting into `other`'s list.
+    pub fn sharing(&self, other: &Warnings) -> Self {
+        Warnings {
+            strict: self.strict,
+            reported: other.reported.clone(),
+        }
+    }
+
+    /// Report a problem the build can work around with `fallback`, or fail in strict mode.
+    pub fn warn(&self, problem: impl Display, fallback: &str) -> Result<()> {
+        if self.strict {
+            return Err(anyhow!("{problem}"));
+        }
+        let warning = format!("{problem}, {fallback}");
+        let mut reported = self.reported.lock().unwrap();
+        if !reported.contains(&warning) {
+            progress::suspend(|| eprintln!("Warning: {warning}"));
+            reported.push(warning);
+        }
+        Ok(())
+    }
+
+    /// Every warning reported so far, in order.
+    pub fn reported(&self) -> Vec<String> {
+        self.reported.lock().unwrap().clone()
+    }
+}
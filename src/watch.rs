@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow};
+use notify::{Event, RecursiveMode, Watcher};
+
+use crate::config::{self, Args, Options};
+use crate::front_matter::is_stdin;
+
+// Editors often save with several writes in quick succession
+const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Rebuild whenever the config file, pages, or anything they use changes. Never returns on
+/// success.
+pub fn watch(args: Args) -> Result<()> {
+    let mut build_args = args.clone();
+    on_change(&args, || {
+        let start = Instant::now();
+        match crate::build(build_args.clone()) {
+            Ok(()) => println!("Rebuilt in {:.2?}", start.elapsed()),
+            Err(e) => eprintln!("Error: {e:#}"),
+        }
+        // Rebuilds show up in the viewer that's already open
+        build_args.open = false;
+        println!("Watching for changes...");
+    })
+}
+
+/// Call `f` once, and then again each time one of the worksheet's inputs changes.
+pub fn on_change(args: &Args, mut f: impl FnMut()) -> Result<()> {
+    if args.pages.iter().any(|page| is_stdin(page)) {
+        return Err(anyhow!("Pages read from stdin can't be watched"));
+    }
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).context("Failed to start file watcher")?;
+    let mut watched_dirs: HashSet<PathBuf> = HashSet::new();
+
+    loop {
+        f();
+
+        // The config may have changed which files we depend on, so refresh the watch list.
+        // Watch parent directories rather than files so editors that save by renaming still
+        // trigger rebuilds.
+        let files = watched_files(args);
+        let dirs: HashSet<PathBuf> = files
+            .iter()
+            .filter_map(|file| file.parent().map(Path::to_path_buf))
+            .collect();
+        for dir in watched_dirs.difference(&dirs) {
+            let _ = watcher.unwatch(dir);
+        }
+        for dir in dirs.difference(&watched_dirs) {
+            if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                eprintln!("Warning: Failed to watch {}: {e}", dir.display());
+            }
+        }
+        watched_dirs = dirs;
+
+        wait_for_change(&rx, &files)?;
+    }
+}
+
+fn watched_files(args: &Args) -> HashSet<PathBuf> {
+    // The config and any configs it extends
+    let mut files: Vec<PathBuf> = args
+        .config
+        .iter()
+        .flat_map(|path| config::config_files(path))
+        .collect();
+    let configs = Options::load_configs(
+        args.config.as_deref(),
+        args.profile.as_deref(),
+        args.target.as_deref(),
+    );
+    for config in configs.into_iter().flatten() {
+        if let Ok(options) = Options::from_args_and_config(args.clone(), config) {
+            files.extend(options.input_files());
+        }
+    }
+
+    files
+        .into_iter()
+        .filter_map(|file| std::path::absolute(file).ok())
+        .collect()
+}
+
+fn wait_for_change(
+    rx: &mpsc::Receiver<notify::Result<Event>>,
+    files: &HashSet<PathBuf>,
+) -> Result<()> {
+    let is_relevant = |event: &Event| {
+        !event.kind.is_access() && event.paths.iter().any(|path| files.contains(path))
+    };
+
+    loop {
+        match rx.recv().context("File watcher stopped unexpectedly")? {
+            Ok(event) if is_relevant(&event) => break,
+            Ok(_) => {}
+            Err(e) => eprintln!("Warning: File watcher error: {e}"),
+        }
+    }
+    while rx.recv_timeout(DEBOUNCE).is_ok() {}
+
+    Ok(())
+}